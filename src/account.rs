@@ -0,0 +1,384 @@
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::*;
+use crate::patterns::*;
+use crate::series::*;
+use crate::strategies::*;
+
+const TAXES_SPOT: f64 = 0.000;
+const TAXES_FUTURES: f64 = 0.0002;
+const MAX_LOT_PRICE: f64 = 1000.;
+const MIN_LOT_PRICE: f64 = 10.;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum CapacityPolicy {
+    // Le signal est ignoré
+    Skip,
+    // Le signal attend qu'une place se libère puis entre au prix d'entrée
+    Queue,
+    // On ferme la position ouverte la moins performante pour prendre le signal
+    ReplaceWeakest,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PortfolioLimits {
+    pub max_open_positions: Option<usize>,
+    // Part maximale du capital engagée dans les positions ouvertes (1. = 100%)
+    pub max_exposure: Option<f64>,
+    pub reserve_capital: bool,
+    pub policy: CapacityPolicy,
+}
+
+impl PortfolioLimits {
+    pub fn unlimited() -> Self {
+        PortfolioLimits {
+            max_open_positions: None,
+            max_exposure: None,
+            reserve_capital: false,
+            policy: CapacityPolicy::Skip,
+        }
+    }
+}
+
+impl Default for PortfolioLimits {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct CostModel {
+    pub spot_fee: f64,
+    pub futures_fee: f64,
+    // Glissement à l'entrée, en fraction du prix
+    pub slippage: f64,
+}
+
+impl CostModel {
+    pub fn entry_cost_rate(&self, market_type: MarketType) -> f64 {
+        let fee = match market_type {
+            MarketType::Spot => self.spot_fee,
+            MarketType::Futures => self.futures_fee,
+        };
+        fee + self.slippage
+    }
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel {
+            spot_fee: TAXES_SPOT,
+            futures_fee: TAXES_FUTURES,
+            slippage: 0.,
+        }
+    }
+}
+
+// Kline courante d'un carnet, lue par index dans la série. La MathKLine complète
+// n'est construite que pour les trades fermés sur cette kline.
+#[derive(Clone, Copy)]
+pub struct Bar<'a> {
+    pub series: SeriesView<'a>,
+    pub klines: &'a dyn KlineSource,
+    pub index: usize,
+}
+
+impl<'a> Bar<'a> {
+    pub fn new(series: SeriesView<'a>, klines: &'a dyn KlineSource, index: usize) -> Self {
+        Bar {
+            series,
+            klines,
+            index,
+        }
+    }
+
+    pub fn high(&self) -> f64 {
+        self.series.high[self.index]
+    }
+
+    pub fn low(&self) -> f64 {
+        self.series.low[self.index]
+    }
+
+    pub fn close(&self) -> f64 {
+        self.series.close[self.index]
+    }
+
+    pub fn close_time(&self) -> i64 {
+        self.series.close_time[self.index]
+    }
+
+    pub fn kline(&self) -> MathKLine {
+        self.klines.kline(self.index)
+    }
+}
+
+pub struct TradeBook {
+    pub trades: Vec<Trade>,
+    start: usize,
+}
+
+impl TradeBook {
+    pub fn new(trades: Vec<Trade>) -> Self {
+        TradeBook { trades, start: 0 }
+    }
+
+    // Si aucun trade n'est ouvert ni en attente, date d'ouverture du prochain signal
+    // après time (i64::MAX s'il n'y en a plus). None si chaque kline doit être traitée.
+    // Les trades sont triés par date d'ouverture.
+    pub fn idle_until(&self, time: i64) -> Option<i64> {
+        for trade in self.trades.iter().skip(self.start) {
+            match trade.status {
+                Status::Running | Status::NotTriggered => return None,
+                Status::NotOpened if trade.open_time >= time => return Some(trade.open_time),
+                _ => {}
+            }
+        }
+        Some(i64::MAX)
+    }
+
+    fn advance_start(&mut self) {
+        while self.start < self.trades.len() && self.trades[self.start].is_finished() {
+            self.start += 1;
+        }
+    }
+}
+
+pub struct Account {
+    pub money: f64,
+    pub reserved: f64,
+    pub open_positions: usize,
+    pub market_type: MarketType,
+    pub limits: PortfolioLimits,
+    pub costs: CostModel,
+    pub money_evolution: Vec<f64>,
+}
+
+impl Account {
+    pub fn new(money: f64, market_type: MarketType, limits: PortfolioLimits) -> Self {
+        Account {
+            money,
+            reserved: 0.,
+            open_positions: 0,
+            market_type,
+            limits,
+            costs: CostModel::default(),
+            money_evolution: Vec::new(),
+        }
+    }
+
+    pub fn available_money(&self) -> f64 {
+        if self.limits.reserve_capital {
+            self.money - self.reserved
+        } else {
+            self.money
+        }
+    }
+
+    // Traite une kline pour chaque carnet de trades (un carnet par symbole).
+    // Retourne false si le capital est épuisé.
+    pub fn process_bars(&mut self, books: &mut [TradeBook], bars: &[Bar]) -> bool {
+        for (book, bar) in books.iter_mut().zip(bars) {
+            book.advance_start();
+            let close_time = bar.close_time();
+            let mut j = book.start;
+            while j < book.trades.len() {
+                let trade = &mut book.trades[j];
+                if trade.open_time > close_time {
+                    break;
+                }
+                if close_time > trade.open_time && trade.status == Status::Running {
+                    self.check_exit(trade, *bar);
+                    if self.money <= 0. {
+                        return false;
+                    }
+                }
+                j += 1;
+            }
+        }
+
+        for b in 0..books.len() {
+            let bar = bars[b];
+            let (high, low, close_time) = (bar.high(), bar.low(), bar.close_time());
+            let mut j = books[b].start;
+            while j < books[b].trades.len() {
+                let trade = &books[b].trades[j];
+                if trade.open_time > close_time {
+                    break;
+                }
+                let is_new_signal =
+                    close_time == trade.open_time && trade.status == Status::NotOpened;
+                let is_queued_signal = trade.status == Status::NotTriggered
+                    && trade.entry_price <= high
+                    && trade.entry_price >= low;
+                if trade.status == Status::NotTriggered && trade.is_invalidated_by(high, low) {
+                    books[b].trades[j].status = Status::Skipped;
+                } else if is_new_signal || is_queued_signal {
+                    self.try_open(books, bars, b, j);
+                }
+                j += 1;
+            }
+        }
+        true
+    }
+
+    // Ferme au prix de clôture toutes les positions ouvertes et annule les ordres en attente
+    pub fn close_positions(&mut self, book: &mut TradeBook, bar: Bar) {
+        for trade in book.trades.iter_mut().skip(book.start) {
+            match trade.status {
+                Status::Running => self.close_at_market(trade, bar, bar.close()),
+                Status::NotTriggered | Status::NotOpened if trade.open_time <= bar.close_time() => {
+                    trade.status = Status::Skipped
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn try_open(&mut self, books: &mut [TradeBook], bars: &[Bar], b: usize, j: usize) {
+        let mut lot_value = self.lot_value();
+
+        if lot_value.is_none() && self.limits.policy == CapacityPolicy::ReplaceWeakest {
+            if let Some((wb, wj)) = Self::find_weakest(books, bars) {
+                // On ne ferme la position la plus faible que si cela libère assez de place
+                let close = bars[wb].close();
+                let weakest = &books[wb].trades[wj];
+                lot_value = self.lot_value_with(
+                    self.money + weakest.unrealized_pnl(close),
+                    self.reserved - weakest.lots * weakest.entry_price,
+                    self.open_positions - 1,
+                );
+                if lot_value.is_some() {
+                    self.close_at_market(&mut books[wb].trades[wj], bars[wb], close);
+                }
+            }
+        }
+
+        let trade = &mut books[b].trades[j];
+        match lot_value {
+            Some(lot_value) => self.open(trade, lot_value),
+            None => match self.limits.policy {
+                CapacityPolicy::Queue => trade.status = Status::NotTriggered,
+                _ => trade.status = Status::Skipped,
+            },
+        }
+    }
+
+    // Valeur de la position à ouvrir, ou None si les limites du portefeuille sont atteintes
+    fn lot_value(&self) -> Option<f64> {
+        self.lot_value_with(self.money, self.reserved, self.open_positions)
+    }
+
+    fn lot_value_with(&self, money: f64, reserved: f64, open_positions: usize) -> Option<f64> {
+        if let Some(max_open_positions) = self.limits.max_open_positions {
+            if open_positions >= max_open_positions {
+                return None;
+            }
+        }
+
+        //Nombre de lots = capital / prix d'entrée, borné entre MIN_LOT_PRICE et MAX_LOT_PRICE
+        let mut lot_value = money.clamp(MIN_LOT_PRICE, MAX_LOT_PRICE);
+
+        if self.limits.reserve_capital {
+            lot_value = lot_value.min(money - reserved);
+        }
+        if let Some(max_exposure) = self.limits.max_exposure {
+            lot_value = lot_value.min(money * max_exposure - reserved);
+        }
+        if (self.limits.reserve_capital || self.limits.max_exposure.is_some())
+            && lot_value < MIN_LOT_PRICE
+        {
+            return None;
+        }
+        Some(lot_value)
+    }
+
+    fn open(&mut self, trade: &mut Trade, lot_value: f64) {
+        // taker et maker 0.1% de frais
+        let lots = lot_value / trade.entry_price;
+        let taxes_rate = self.costs.entry_cost_rate(self.market_type);
+
+        let taxes = lots * trade.entry_price * taxes_rate;
+        self.money -= taxes;
+        trade.status = Status::Running;
+        trade.money = self.money;
+        trade.lots = lots;
+        trade.taxes = taxes;
+
+        if trade.is_long() {
+            trade.benefits = lots * trade.tp - lots * trade.entry_price;
+            trade.loss = lots * trade.entry_price - lots * trade.sl;
+        } else {
+            trade.benefits = lots * trade.entry_price - lots * trade.tp;
+            trade.loss = lots * trade.sl - lots * trade.entry_price;
+        }
+
+        self.reserved += lot_value;
+        self.open_positions += 1;
+    }
+
+    fn check_exit(&mut self, trade: &mut Trade, bar: Bar) {
+        let (high, low) = (bar.high(), bar.low());
+        if trade.tp > trade.sl {
+            //Si le trade est Long
+            if low <= trade.sl && high >= trade.tp {
+                self.close(trade, bar, TradeResult::Unknown);
+            } else if low <= trade.sl {
+                self.close(trade, bar, TradeResult::Lost);
+            } else if high >= trade.tp {
+                self.close(trade, bar, TradeResult::Win);
+            }
+        } else if trade.tp < trade.sl {
+            //Si le trade est short
+            if low <= trade.tp && high >= trade.sl {
+                self.close(trade, bar, TradeResult::Unknown);
+            } else if high >= trade.sl {
+                self.close(trade, bar, TradeResult::Lost);
+            } else if low <= trade.tp {
+                self.close(trade, bar, TradeResult::Win);
+            }
+        }
+    }
+
+    fn close(&mut self, trade: &mut Trade, bar: Bar, result: TradeResult) {
+        match result {
+            TradeResult::Win => self.money += trade.benefits,
+            TradeResult::Lost => self.money -= trade.loss,
+            _ => {}
+        }
+        if result != TradeResult::Unknown {
+            self.money_evolution.push(self.money);
+        }
+        self.release(trade, bar, result);
+    }
+
+    fn close_at_market(&mut self, trade: &mut Trade, bar: Bar, price: f64) {
+        self.money += trade.unrealized_pnl(price);
+        self.money_evolution.push(self.money);
+        self.release(trade, bar, TradeResult::Replaced);
+    }
+
+    fn release(&mut self, trade: &mut Trade, bar: Bar, result: TradeResult) {
+        trade.status = Status::Closed(result);
+        trade.close_time = bar.close_time();
+        trade.closing_kline = Some(bar.kline());
+        self.reserved -= trade.lots * trade.entry_price;
+        self.open_positions -= 1;
+    }
+
+    fn find_weakest(books: &[TradeBook], bars: &[Bar]) -> Option<(usize, usize)> {
+        let mut weakest: Option<(usize, usize, f64)> = None;
+        for (b, book) in books.iter().enumerate() {
+            for (j, trade) in book.trades.iter().enumerate().skip(book.start) {
+                if trade.status != Status::Running {
+                    continue;
+                }
+                let pnl = trade.unrealized_pnl(bars[b].close());
+                if weakest.is_none_or(|(_, _, weakest_pnl)| pnl < weakest_pnl) {
+                    weakest = Some((b, j, pnl));
+                }
+            }
+        }
+        weakest.map(|(b, j, _)| (b, j))
+    }
+}
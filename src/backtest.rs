@@ -1,36 +1,39 @@
-use std::collections::HashMap;
-use std::io::Write;
-use std::sync::mpsc::{channel, Sender};
+use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
-use std::{fmt, io, thread};
-
+use std::fmt;
+
+use crate::account::*;
+use crate::benchmark::*;
+use crate::engine::*;
+use crate::error::{Error, Result};
+use crate::filters::*;
+use crate::lookahead::*;
 use crate::patterns::*;
+use crate::progress::*;
+use crate::series::*;
+use crate::storage::*;
 use crate::strategies::*;
 use binance::model::{KlineSummary, Kline};
 use chrono::Duration;
 use serde::{Deserialize, Serialize};
 
 pub type StrategyFunc = fn(
-    &Vec<MathKLine>,
-    Option<&Sender<f32>>,
+    &dyn KlineSource,
+    SeriesView,
+    Option<&ProgressTracker>,
     StrategyParams,
     Arc<Vec<Arc<dyn PatternParams>>>,
     bool
-) -> Vec<Trade>;
+) -> Result<Vec<Trade>>;
 pub type Strategy = (
     StrategyFunc,
     StrategyParams,
     Arc<Vec<Arc<dyn PatternParams>>>,
 );
 
-const TAXES_SPOT: f64 = 0.000;
-const TAXES_FUTURES: f64 = 0.0002;
 const MAX_LEVERAGE: f64 = 1.;
 const MAX_BENEFITS: f64 = 1000.;
 const MIN_LOSS: f64 = 0.001;
-const MAX_LOT_PRICE: f64 = 1000.;
-const MIN_LOT_PRICE: f64 = 10.;
 
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum StrategyName {
@@ -38,6 +41,7 @@ pub enum StrategyName {
     W,
     M,
     BullReversal,
+    Confluence,
 }
 
 impl fmt::Display for StrategyName {
@@ -47,6 +51,7 @@ impl fmt::Display for StrategyName {
             StrategyName::W => write!(f, "W"),
             StrategyName::M => write!(f, "M"),
             StrategyName::BullReversal => write!(f, "Bull Reversal"),
+            StrategyName::Confluence => write!(f, "Confluence"),
         }
     }
 }
@@ -57,13 +62,15 @@ pub enum Status {
     NotTriggered,
     Running,
     Closed(TradeResult),
+    Skipped,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum TradeResult {
     Win,
     Lost,
     Unknown,
+    Replaced,
 }
 
 #[derive(Clone, Debug)]
@@ -84,11 +91,96 @@ pub struct Trade {
     pub strategy: StrategyName,
 }
 
+impl Trade {
+    pub fn is_long(&self) -> bool {
+        self.sl < self.tp
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status, Status::Closed(_) | Status::Skipped)
+    }
+
+    pub fn unrealized_pnl(&self, price: f64) -> f64 {
+        if self.is_long() {
+            self.lots * (price - self.entry_price)
+        } else {
+            self.lots * (self.entry_price - price)
+        }
+    }
+
+    pub fn realized_pnl(&self) -> f64 {
+        match self.status {
+            Status::Closed(TradeResult::Win) => self.benefits - self.taxes,
+            Status::Closed(TradeResult::Lost) => -self.loss - self.taxes,
+            Status::Closed(TradeResult::Replaced) => match &self.closing_kline {
+                Some(kline) => self.unrealized_pnl(kline.close) - self.taxes,
+                None => -self.taxes,
+            },
+            Status::Closed(TradeResult::Unknown) | Status::Running => -self.taxes,
+            _ => 0.,
+        }
+    }
+
+    // Un trade en attente n'est plus valable si le TP ou le SL est touché avant l'entrée
+    pub fn is_invalidated_by(&self, high: f64, low: f64) -> bool {
+        if self.is_long() {
+            high >= self.tp || low <= self.sl
+        } else {
+            low <= self.tp || high >= self.sl
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct TradeRecord {
+    pub open_time: i64,
+    pub close_time: i64,
+    pub entry_price: f64,
+    pub sl: f64,
+    pub tp: f64,
+    pub lots: f64,
+    // Capital au moment de l'ouverture du trade
+    pub money: f64,
+    pub pnl: f64,
+    pub result: TradeResult,
+}
+
+impl TradeRecord {
+    pub fn ledger(trades: &[Trade]) -> Vec<TradeRecord> {
+        let mut ledger: Vec<TradeRecord> = trades
+            .iter()
+            .filter_map(|trade| match trade.status {
+                Status::Closed(result) => Some(TradeRecord {
+                    open_time: trade.open_time,
+                    close_time: trade.close_time,
+                    entry_price: trade.entry_price,
+                    sl: trade.sl,
+                    tp: trade.tp,
+                    lots: trade.lots,
+                    money: trade.money,
+                    pnl: trade.realized_pnl(),
+                    result,
+                }),
+                _ => None,
+            })
+            .collect();
+        ledger.sort_by_key(|record| record.close_time);
+        ledger
+    }
+
+    pub fn return_ratio(&self) -> f64 {
+        if self.money <= 0. {
+            return 0.;
+        }
+        self.pnl / self.money
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StrategyResult {
     pub name: StrategyName,
     pub strategy_params: StrategyParams,
-    pub patterns_params: HashMap<String, String>,
+    pub patterns_params: Vec<PatternConfig>,
     pub win_ratio: f32,
     pub lose_ratio: f32,
     pub unknown_ratio: f32,
@@ -96,297 +188,382 @@ pub struct StrategyResult {
     pub total_lose: usize,
     pub total_closed: usize,
     pub total_unclosed: usize,
+    #[serde(default)]
+    pub total_skipped: usize,
     pub rr_ratio: f32,
     pub rr_lisible: String,
     pub efficiency: f32,
+    #[serde(default)]
+    pub start_money: f64,
     pub final_money: f64,
     pub money_evolution: Vec<f64>,
+    #[serde(default)]
+    pub ledger: Option<Vec<TradeRecord>>,
+    #[serde(default)]
+    pub benchmark: Option<Benchmark>,
+}
+
+impl StrategyResult {
+    // Valeur d'un paramètre de pattern, par exemple "klines_range"
+    pub fn pattern_param(&self, name: &str) -> Option<f64> {
+        self.patterns_params.iter().find_map(|params| params.get(name))
+    }
+
+    pub fn from_trades(
+        start_money: f64,
+        strategy_params: StrategyParams,
+        patterns_params: &[Arc<dyn PatternParams>],
+        trades: &[Trade],
+        money_evolution: Vec<f64>,
+    ) -> Self {
+        let name = strategy_params.name;
+
+        let total_win = trades
+            .iter()
+            .filter(|&trade| trade.status == Status::Closed(TradeResult::Win))
+            .count();
+        let total_lose = trades
+            .iter()
+            .filter(|&trade| trade.status == Status::Closed(TradeResult::Lost))
+            .count();
+        let total_unknown = trades
+            .iter()
+            .filter(|&trade| trade.status == Status::Closed(TradeResult::Unknown))
+            .count();
+        let total_closed = trades
+            .iter()
+            .filter(|&trade| matches!(trade.status, Status::Closed { .. }))
+            .count();
+        let total_skipped = trades
+            .iter()
+            .filter(|&trade| trade.status == Status::Skipped)
+            .count();
+        let total_unclosed = trades.len() - total_closed - total_skipped;
+
+        let win_ratio = (total_win as f32 * 100. / total_closed as f32 * 100.0).round() / 100.0;
+        let lose_ratio = (total_lose as f32 * 100. / total_closed as f32 * 100.0).round() / 100.0;
+        let unknown_ratio =
+            (total_unknown as f32 * 100. / total_closed as f32 * 100.0).round() / 100.0;
+        let needed_win_percentage =
+            (((1. / (1. + (strategy_params.tp_multiplier / strategy_params.sl_multiplier)) * 100.) * 100.0)
+                .round()
+                / 100.0) as f32;
+        let efficiency = (win_ratio / needed_win_percentage * 100.0).round() / 100.0;
+        let final_money = strategy_params.money;
+
+        StrategyResult {
+            name,
+            strategy_params,
+            patterns_params: patterns_params.iter().map(|params| params.to_config()).collect(),
+            win_ratio,
+            lose_ratio,
+            unknown_ratio,
+            total_win,
+            total_lose,
+            total_closed,
+            total_unclosed,
+            total_skipped,
+            rr_ratio: (needed_win_percentage * 0.01 * 100.0).round() / 100.0,
+            rr_lisible: format!(
+                "{}:{}",
+                (strategy_params.tp_multiplier * (1. / strategy_params.sl_multiplier) * 100.0).round()
+                    / 100.0,
+                strategy_params.sl_multiplier * (1. / strategy_params.sl_multiplier)
+            ),
+            efficiency,
+            start_money,
+            final_money,
+            money_evolution,
+            ledger: None,
+            benchmark: None,
+        }
+    }
 }
 
 pub struct Backtester {
-    klines_data: Arc<Vec<MathKLine>>,
+    klines_data: Arc<dyn KlineSource>,
+    series: PriceSeries,
     trades: Vec<Trade>,
     strategies: Vec<Strategy>,
     results: Vec<StrategyResult>,
     current_strategy_money_evolution: Vec<f64>,
-    progression_tracker: Option<Sender<(f32, usize)>>,
+    progression_tracker: Option<Sender<ProgressEvent>>,
+    cancellation: CancellationToken,
     id: Option<usize>,
-    only_potential: bool
+    only_potential: bool,
+    portfolio_limits: PortfolioLimits,
+    keep_ledger: bool,
+    cost_model: CostModel,
+    benchmark: Option<BenchmarkConfig>,
+    signal_filters: Vec<Arc<dyn SignalFilter>>,
+    result_store: Option<Arc<Mutex<ResultStore>>>,
 }
 
 impl Backtester {
     pub fn new(
         klines_data: Arc<Vec<MathKLine>>,
-        progression_tracker: Option<Sender<(f32, usize)>>,
+        progression_tracker: Option<Sender<ProgressEvent>>,
+        id: Option<usize>,
+        only_potential: bool
+    ) -> Self {
+        let series = PriceSeries::from_klines(&klines_data);
+        Self::with_series(klines_data, series, progression_tracker, id, only_potential)
+    }
+
+    // series doit contenir les mêmes klines que klines_data, par exemple un KlineFile et
+    // KlineFile::to_series : les MathKLine ne sont alors construites que pour les trades
+    pub fn with_series(
+        klines_data: Arc<dyn KlineSource>,
+        series: PriceSeries,
+        progression_tracker: Option<Sender<ProgressEvent>>,
         id: Option<usize>,
         only_potential: bool
     ) -> Self {
         Backtester {
+            series,
             klines_data,
             trades: Vec::new(),
             strategies: Vec::new(),
             results: Vec::new(),
             current_strategy_money_evolution: Vec::new(),
             progression_tracker,
+            cancellation: CancellationToken::new(),
             id,
-            only_potential
+            only_potential,
+            portfolio_limits: PortfolioLimits::unlimited(),
+            keep_ledger: false,
+            cost_model: CostModel::default(),
+            benchmark: None,
+            signal_filters: Vec::new(),
+            result_store: None,
         }
     }
 
-    pub fn start(&mut self) -> &mut Self {
-        let size = self.strategies.len();
-        let start = Instant::now();
-
-        let progression_tracker_clone = self.progression_tracker.clone();
-        let id = self.id;
-        let total = self.strategies.len();
-        let current: Arc<Mutex<f32>> = Arc::new(Mutex::new(1.));
-        let current_clone: Arc<Mutex<f32>> = current.clone();
-        let option_tx;
-        let (tx, rx) = channel::<f32>();
-        option_tx = if let Some(tracker) = progression_tracker_clone {
-            thread::spawn(move || loop {
-                while let Ok(progression) = rx.recv() {
-                    let current_num = *current.lock().unwrap();
-                    let total_sent = ((progression + ((current_num - 1.) * 100.)) / (total * 100) as f32) * 100.;
-                    //println!("progression == {} ---- total sent == {} ---- current == {}", progression, total_sent, current_num);
-                    tracker.send((total_sent, id.unwrap()));
-                }
-            });
-            Some(&tx)
-        } else {
-            None
-        };
-
+    pub fn start(&mut self) -> Result<&mut Self> {
+        let mut tracker = self.new_tracker();
+        let dataset_hash = self.result_store.as_ref().map(|_| dataset_hash(self.series.view()));
         for (i, strategy) in self.strategies.clone().iter_mut().enumerate() {
-            self.create_trades_from_strategy(strategy.clone(), option_tx);
-            self.resolve_trades(strategy, option_tx);
-            self.generate_results(strategy);
-            self.clean_trades();
-
-            let new_current = *current_clone.lock().unwrap() + 1.;
-            *current_clone.lock().unwrap() = new_current;
+            if tracker.is_cancelled() {
+                break;
+            }
+            tracker.strategy_started(i, strategy.1.name);
+            let params_hash = params_hash(
+                strategy,
+                &self.signal_filters,
+                &self.portfolio_limits,
+                &self.cost_model,
+                self.only_potential,
+                &self.benchmark,
+            );
+            let from_store = if let Some(result) = self.stored_result(&dataset_hash, &params_hash) {
+                self.results.push(result);
+                true
+            } else {
+                // Une stratégie interrompue n'a pas de résultat, seules les précédentes sont gardées
+                match self.run_strategy(strategy, &tracker) {
+                    Err(Error::Cancelled) => {
+                        self.clean_trades();
+                        break;
+                    }
+                    result => result?,
+                }
+                self.store_last_result(&dataset_hash, &params_hash);
+                false
+            };
+            tracker.strategy_finished(from_store);
         }
-        if let Some(tracker) = &self.progression_tracker {
-            tracker.send((100., self.id.unwrap()));
+        tracker.finished(self.results.len());
+        Ok(self)
+    }
+
+    // Rejoue chaque stratégie kline par kline, sans accès aux klines futures
+    pub fn start_event_driven(&mut self) -> Result<&mut Self> {
+        let mut tracker = self.new_tracker();
+        for (i, strategy) in self.strategies.clone().into_iter().enumerate() {
+            if tracker.is_cancelled() {
+                break;
+            }
+            validate_strategy(&strategy.1, &strategy.2)?;
+            if trade_finder(strategy.1.name).is_none() {
+                return Err(Error::InvalidParams(format!(
+                    "strategy {} cannot be run event-driven",
+                    strategy.1.name
+                )));
+            }
+            tracker.strategy_started(i, strategy.1.name);
+            let mut engine = EventEngine::new(
+                Box::new(PatternBarStrategy::new(strategy)),
+                self.portfolio_limits,
+            );
+            engine.set_cost_model(self.cost_model);
+            for filter in self.signal_filters.iter() {
+                engine.add_signal_filter(filter.clone());
+            }
+            let series = self.series.view();
+            for i in 0..series.len() {
+                engine.replay_kline(series, &*self.klines_data, i);
+            }
+            let mut result = engine.result();
+            if let Some(config) = self.benchmark {
+                result.benchmark = Some(Benchmark::compute(
+                    &result,
+                    self.series.view(),
+                    &*self.klines_data,
+                    engine.trades(),
+                    self.portfolio_limits,
+                    self.cost_model,
+                    config,
+                ));
+            }
+            if !self.keep_ledger {
+                result.ledger = None;
+            }
+            self.results.push(result);
+            tracker.strategy_finished(false);
         }
-        self
+        tracker.finished(self.results.len());
+        Ok(self)
+    }
+
+    pub fn check_lookahead(&self, step: usize) -> Result<Vec<LookaheadReport>> {
+        self.strategies
+            .iter()
+            .map(|strategy| {
+                check_lookahead(self.series.view(), &*self.klines_data, strategy, step)
+            })
+            .collect()
     }
 
-    pub fn start_potential_only(&mut self) -> &Vec<Trade> {
+    pub fn start_potential_only(&mut self) -> Result<&Vec<Trade>> {
         for strategy in self.strategies.clone().iter_mut() {
-            self.create_trades_from_strategy(strategy.clone(), None);
+            self.create_trades_from_strategy(strategy.clone(), None)?;
         }
-        &self.trades
+        Ok(&self.trades)
+    }
+
+    fn run_strategy(&mut self, strategy: &mut Strategy, tracker: &ProgressTracker) -> Result<()> {
+        let start_money = strategy.1.money;
+        self.create_trades_from_strategy(strategy.clone(), Some(tracker))?;
+        tracker.trades_found(self.trades.len());
+        self.resolve_trades(strategy, tracker)?;
+        self.generate_results(strategy, start_money);
+        self.clean_trades();
+        Ok(())
     }
 
     fn create_trades_from_strategy(
         &mut self,
         strategy: Strategy,
-        progression_tracker: Option<&Sender<f32>>,
-    ) {
+        progression_tracker: Option<&ProgressTracker>,
+    ) -> Result<()> {
+        if let Some(tracker) = progression_tracker {
+            tracker.check_cancelled()?;
+        }
         self.trades = strategy.0(
-            &self.klines_data,
+            &*self.klines_data,
+            self.series.view(),
             progression_tracker,
             strategy.1,
             strategy.2,
             self.only_potential
-        );
+        )?;
+        let filters = &self.signal_filters;
+        self.trades.retain(|trade| filters.iter().all(|filter| filter.accepts(trade)));
+        Ok(())
     }
 
     fn resolve_trades(
         &mut self,
         strategy: &mut Strategy,
-        progression_tracker: Option<&Sender<f32>>,
-    ) {
+        progression_tracker: &ProgressTracker,
+    ) -> Result<()> {
         let mut last_sent = 0;
-        let mut start = 0;
-        for (i, kline) in self.klines_data.iter().enumerate() {
-            let mut j = start;
-            while j < self.trades.len() {
-                let mut trade = &mut self.trades[j];
-                if let Status::Closed(_) = trade.status {
-                    if j == start + 1 {
-                        start = j;
-                    }
-                    j += 1;
-                    continue;
-                } else if trade.open_time > kline.close_time {
-                    break;
-                }
-                if kline.close_time == trade.open_time && trade.status == Status::NotOpened {
-                    trade.status = Status::Running;
-                    // taker et maker 0.1% de frais
-                    //Nombre de lots = (Capital de départ x % de capital risqué dans le trade x Ratio risque/récompense) / (Prix d'entrée - Prix de stop-loss)
-                    let entry_stop_diff = if trade.sl < trade.tp {
-                        trade.entry_price - trade.sl
-                    } else {
-                        trade.sl - trade.entry_price
-                    };
-                    let mut lots = (strategy.1.money / trade.entry_price);
-                    if lots * trade.entry_price > MAX_LOT_PRICE {
-                        lots = MAX_LOT_PRICE / trade.entry_price;
-                    } else if lots * trade.entry_price < MIN_LOT_PRICE {
-                        lots = MIN_LOT_PRICE / trade.entry_price;
-                    }
-                        //* strategy.1.risk_per_trade
-                        //* (strategy.1.sl_multiplier / strategy.1.tp_multiplier))
-                        // / entry_stop_diff;
-                    let taxes_rate;
-                    //println!("lots = {}", lots);
-
-                    match strategy.1.market_type {
-                        MarketType::Spot => {
-                            taxes_rate = TAXES_SPOT;
-                            //if lots * trade.entry_price > strategy.1.money {
-                            //    lots = (strategy.1.money * MAX_LEVERAGE) / trade.entry_price;
-                            //}
-                        }
-                        MarketType::Futures => {
-                            taxes_rate = TAXES_FUTURES;
-                        }
-                    }
-
-                    let taxes = lots * trade.entry_price * taxes_rate;
-                    strategy.1.money -= taxes;
-                    trade.money = strategy.1.money;
-                    trade.lots = lots;
-                    trade.taxes = taxes;
-
-                    //trade.benefits =
-                    //    trade.money * strategy.1.risk_per_trade * strategy.1.tp_multiplier;
-                    //trade.loss = trade.money * strategy.1.risk_per_trade * strategy.1.sl_multiplier;
-
-                    if trade.sl < trade.tp { //Si c'est un trade long
-                        trade.benefits = lots * trade.tp - lots * trade.entry_price;
-                        trade.loss = lots * trade.entry_price - lots * trade.sl;
-                    } else {//Si c'est un trade short
-                        trade.benefits = lots * trade.entry_price - lots * trade.tp;
-                        trade.loss = lots * trade.sl - lots * trade.entry_price;
-                    }
-                    //if trade.benefits > MAX_BENEFITS {
-                    //    trade.benefits = MAX_BENEFITS;
-                    //}
-                    //if trade.loss < MIN_LOSS {
-                    //    trade.loss = MIN_LOSS;
-                    //}
-
-                    //let leverage = (((trade.money + trade.benefits) / trade.money) - 1.) / ((trade.tp / trade.entry_price) - 1.);
-                    //println!("leverage == {} for trade {:#?}", leverage, trade);
-                    if trade.entry_price <= kline.high
-                        && trade.entry_price >= kline.low
-                        && trade.status == Status::NotTriggered
-                    {
-                        trade.status = Status::Running;
-                        //((Cap/Cav)-1)/((Pv/Pa)-1)
-                    }
-                }
-
-                if kline.close_time > trade.open_time && trade.status == Status::Running {
-                    if trade.tp > trade.sl { //Si le trade est Long
-                        if kline.low <= trade.sl && kline.high >= trade.tp {
-                            trade.status = Status::Closed(TradeResult::Unknown);
-                        } else if kline.low <= trade.sl {
-                            trade.status = Status::Closed(TradeResult::Lost);
-                            strategy.1.money -= trade.loss;
-                            self.current_strategy_money_evolution.push(strategy.1.money);
-                        } else if kline.high >= trade.tp {
-                            trade.status = Status::Closed(TradeResult::Win);
-                            strategy.1.money += trade.benefits;
-                            self.current_strategy_money_evolution.push(strategy.1.money);
-                        }
-                    } else if trade.tp < trade.sl { //Si le trade est short
-                        if kline.low <= trade.tp && kline.high >= trade.sl {
-                            trade.status = Status::Closed(TradeResult::Unknown);
-                        } else if kline.high >= trade.sl {
-                            trade.status = Status::Closed(TradeResult::Lost);
-                            strategy.1.money -= trade.loss;
-                            self.current_strategy_money_evolution.push(strategy.1.money);
-                        } else if kline.low <= trade.tp {
-                            trade.status = Status::Closed(TradeResult::Win);
-                            strategy.1.money += trade.benefits;
-                            self.current_strategy_money_evolution.push(strategy.1.money);
-                        }
-                    }
-                    if strategy.1.money <= 0. {
-                        return;
-                    }
-                }
-                j += 1;
+        let mut account = Account::new(strategy.1.money, strategy.1.market_type, self.portfolio_limits);
+        account.costs = self.cost_model;
+        let mut books = [TradeBook::new(std::mem::take(&mut self.trades))];
+        let series = self.series.view();
+        let mut i = 0;
+        while i < series.len() {
+            if !account.process_bars(&mut books, &[Bar::new(series, &*self.klines_data, i)]) {
+                break;
             }
             if last_sent + 1000 < i {
-                let to_send = (i as f32 / self.klines_data.len() as f32 * 50.) + 50.;
-                if let Some(tracker) = progression_tracker {
-                    tracker.send(to_send);
-                }
+                progression_tracker.check_cancelled()?;
+                progression_tracker.report(i as f32 / series.len() as f32 * 0.5 + 0.5);
                 last_sent = i;
             }
+            // Sans position ni ordre en attente, on passe directement à la kline du prochain signal
+            i = match books[0].idle_until(series.close_time[i]) {
+                Some(time) => series.first_closing_at(time).max(i + 1),
+                None => i + 1,
+            };
         }
+        let [book] = books;
+        self.trades = book.trades;
+        strategy.1.money = account.money;
+        self.current_strategy_money_evolution = account.money_evolution;
+        Ok(())
     }
 
-    fn generate_results(&mut self, strategy: &Strategy) {
-        let name = strategy.1.name;
-        let mut patterns_params = HashMap::new();
-
-        for params in strategy.2.as_ref() {
-            patterns_params.extend(params.get_params());
+    fn generate_results(&mut self, strategy: &Strategy, start_money: f64) {
+        let mut result = StrategyResult::from_trades(
+            start_money,
+            strategy.1,
+            &strategy.2,
+            &self.trades,
+            self.current_strategy_money_evolution.clone(),
+        );
+        if self.keep_ledger {
+            result.ledger = Some(TradeRecord::ledger(&self.trades));
+        }
+        if let Some(config) = self.benchmark {
+            result.benchmark = Some(Benchmark::compute(
+                &result,
+                self.series.view(),
+                &*self.klines_data,
+                &self.trades,
+                self.portfolio_limits,
+                self.cost_model,
+                config,
+            ));
         }
+        self.results.push(result);
+    }
 
-        let total_win = self
-            .trades
-            .iter()
-            .filter(|&trade| trade.status == Status::Closed(TradeResult::Win))
-            .count();
-        let total_lose = self
-            .trades
-            .iter()
-            .filter(|&trade| trade.status == Status::Closed(TradeResult::Lost))
-            .count();
-        let total_unknown = self
-            .trades
-            .iter()
-            .filter(|&trade| trade.status == Status::Closed(TradeResult::Unknown))
-            .count();
-        let total_closed = self
-            .trades
-            .iter()
-            .filter(|&trade| matches!(trade.status, Status::Closed { .. }))
-            .count();
-        let total_unclosed = self.trades.len() - total_closed;
+    // Résultat déjà calculé sur les mêmes données lors d'une exécution précédente
+    fn stored_result(
+        &self,
+        dataset_hash: &Option<String>,
+        params_hash: &str,
+    ) -> Option<StrategyResult> {
+        let store = self.result_store.as_ref()?.lock().unwrap();
+        let mut result = store.get(dataset_hash.as_ref()?, params_hash).ok()??;
+        if self.keep_ledger && result.ledger.is_none() {
+            return None;
+        }
+        if !self.keep_ledger {
+            result.ledger = None;
+        }
+        Some(result)
+    }
 
-        let win_ratio = (total_win as f32 * 100. / total_closed as f32 * 100.0).round() / 100.0;
-        let lose_ratio = (total_lose as f32 * 100. / total_closed as f32 * 100.0).round() / 100.0;
-        let unknown_ratio =
-            (total_unknown as f32 * 100. / total_closed as f32 * 100.0).round() / 100.0;
-        let needed_win_percentage =
-            (((1. / (1. + (strategy.1.tp_multiplier / strategy.1.sl_multiplier)) * 100.) * 100.0)
-                .round()
-                / 100.0) as f32;
-        let efficiency = (win_ratio / needed_win_percentage * 100.0).round() / 100.0;
-        let final_money = strategy.1.money;
+    fn store_last_result(&self, dataset_hash: &Option<String>, params_hash: &str) {
+        if let (Some(store), Some(dataset_hash), Some(result)) =
+            (&self.result_store, dataset_hash, self.results.last())
+        {
+            if let Err(error) = store.lock().unwrap().insert(dataset_hash, params_hash, result) {
+                log::warn!("Could not store result {}: {}", params_hash, error);
+            }
+        }
+    }
 
-        self.results.push(StrategyResult {
-            name,
-            strategy_params: strategy.1,
-            patterns_params,
-            win_ratio,
-            lose_ratio,
-            unknown_ratio,
-            total_win,
-            total_lose,
-            total_closed,
-            total_unclosed,
-            rr_ratio: (needed_win_percentage * 0.01 * 100.0).round() / 100.0,
-            rr_lisible: format!(
-                "{}:{}",
-                (strategy.1.tp_multiplier * (1. / strategy.1.sl_multiplier) * 100.0).round()
-                    / 100.0,
-                strategy.1.sl_multiplier * (1. / strategy.1.sl_multiplier)
-            ),
-            efficiency,
-            final_money,
-            money_evolution: self.current_strategy_money_evolution.clone(),
-        });
+    fn new_tracker(&self) -> ProgressTracker {
+        ProgressTracker::new(
+            self.progression_tracker.clone(),
+            self.cancellation.clone(),
+            self.id,
+            self.strategies.len(),
+        )
     }
 
     fn clean_trades(&mut self) {
@@ -394,6 +571,48 @@ impl Backtester {
         self.current_strategy_money_evolution.clear();
     }
 
+    pub fn set_portfolio_limits(&mut self, limits: PortfolioLimits) -> &mut Self {
+        self.portfolio_limits = limits;
+        self
+    }
+
+    pub fn set_cost_model(&mut self, cost_model: CostModel) -> &mut Self {
+        self.cost_model = cost_model;
+        self
+    }
+
+    pub fn keep_ledger(&mut self, keep_ledger: bool) -> &mut Self {
+        self.keep_ledger = keep_ledger;
+        self
+    }
+
+    // Compare chaque résultat au buy-and-hold et à des entrées aléatoires
+    pub fn set_benchmark(&mut self, config: BenchmarkConfig) -> &mut Self {
+        self.benchmark = Some(config);
+        self
+    }
+
+    // Les trades refusés par un filtre sont écartés avant leur résolution
+    pub fn add_signal_filter(&mut self, filter: Arc<dyn SignalFilter>) -> &mut Self {
+        self.signal_filters.push(filter);
+        self
+    }
+
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancellation = token;
+        self
+    }
+
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    // Les résultats sont enregistrés au fil de l'eau et ceux déjà présents ne sont pas recalculés
+    pub fn set_result_store(&mut self, store: Arc<Mutex<ResultStore>>) -> &mut Self {
+        self.result_store = Some(store);
+        self
+    }
+
     pub fn add_strategy(&mut self, strategy: Strategy) -> &mut Self {
         self.strategies.push(strategy);
         self
@@ -499,43 +718,80 @@ impl Backtester {
         price <= kline.high && price >= kline.low
     }*/
 
-    pub fn kline_summary_to_math_kline(kline: &KlineSummary) -> MathKLine {
-        MathKLine {
+    pub fn kline_summary_to_math_kline(kline: &KlineSummary) -> Result<MathKLine> {
+        Ok(MathKLine {
             open_time: kline.open_time,
-            open: kline.open.parse::<f64>().unwrap(),
-            high: kline.high.parse::<f64>().unwrap(),
-            low: kline.low.parse::<f64>().unwrap(),
-            close: kline.close.parse::<f64>().unwrap(),
+            open: parse_price("open", &kline.open)?,
+            high: parse_price("high", &kline.high)?,
+            low: parse_price("low", &kline.low)?,
+            close: parse_price("close", &kline.close)?,
             volume: kline.volume.clone(),
             close_time: kline.close_time,
             quote_asset_volume: kline.quote_asset_volume.clone(),
             number_of_trades: kline.number_of_trades,
             taker_buy_base_asset_volume: kline.taker_buy_base_asset_volume.clone(),
             taker_buy_quote_asset_volume: kline.taker_buy_quote_asset_volume.clone(),
-        }
+        })
     }
 
-    pub fn kline_to_math_kline(kline: &Kline) -> MathKLine {
-        MathKLine {
+    pub fn kline_to_math_kline(kline: &Kline) -> Result<MathKLine> {
+        Ok(MathKLine {
             open_time: kline.open_time,
-            open: kline.open.parse::<f64>().unwrap(),
-            high: kline.high.parse::<f64>().unwrap(),
-            low: kline.low.parse::<f64>().unwrap(),
-            close: kline.close.parse::<f64>().unwrap(),
+            open: parse_price("open", &kline.open)?,
+            high: parse_price("high", &kline.high)?,
+            low: parse_price("low", &kline.low)?,
+            close: parse_price("close", &kline.close)?,
             volume: kline.volume.clone(),
             close_time: kline.close_time,
             quote_asset_volume: kline.quote_asset_volume.clone(),
             number_of_trades: kline.number_of_trades,
             taker_buy_base_asset_volume: kline.taker_buy_base_asset_volume.clone(),
             taker_buy_quote_asset_volume: kline.taker_buy_quote_asset_volume.clone(),
-        }
+        })
+    }
+
+    pub fn to_all_math_kline(klines: Vec<KlineSummary>) -> Result<Vec<MathKLine>> {
+        klines.iter().map(Self::kline_summary_to_math_kline).collect()
     }
 
-    pub fn to_all_math_kline(klines: Vec<KlineSummary>) -> Vec<MathKLine> {
-        let mut result: Vec<MathKLine> = Vec::new();
-        for kline in klines.iter() {
-            result.push(Self::kline_summary_to_math_kline(kline));
+    // Prix cohérents et klines triées sans doublon
+    pub fn validate_klines(klines: &[MathKLine]) -> Result<()> {
+        Self::validate_series(PriceSeries::from_klines(klines).view())
+    }
+
+    pub fn validate_series(series: SeriesView) -> Result<()> {
+        for i in 0..series.len() {
+            let (open, high, low, close) =
+                (series.open[i], series.high[i], series.low[i], series.close[i]);
+            if low > high || open < low || open > high || close < low || close > high {
+                return Err(Error::InvalidData(format!(
+                    "kline {} at {} has prices outside of its low/high range",
+                    i, series.open_time[i]
+                )));
+            }
+            if series.close_time[i] < series.open_time[i] {
+                return Err(Error::InvalidData(format!(
+                    "kline {} at {} closes before it opens",
+                    i, series.open_time[i]
+                )));
+            }
+            if i > 0 && series.open_time[i] <= series.open_time[i - 1] {
+                return Err(Error::InvalidData(format!(
+                    "kline {} at {} is not after the previous one",
+                    i, series.open_time[i]
+                )));
+            }
         }
-        result
+        Ok(())
+    }
+}
+
+fn parse_price(field: &'static str, value: &str) -> Result<f64> {
+    match value.parse::<f64>() {
+        Ok(price) if price.is_finite() && price >= 0. => Ok(price),
+        _ => Err(Error::Parse {
+            field,
+            value: value.to_string(),
+        }),
     }
 }
@@ -0,0 +1,192 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::account::*;
+use crate::backtest::*;
+use crate::metrics::*;
+use crate::series::*;
+use crate::strategies::*;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct BenchmarkConfig {
+    // Nombre de tirages d'entrées aléatoires moyennés
+    pub random_runs: usize,
+    pub seed: u64,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        BenchmarkConfig {
+            random_runs: 20,
+            seed: 0,
+        }
+    }
+}
+
+// Comparaison d'un résultat au marché sur la même période, en fraction du capital
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Benchmark {
+    pub buy_and_hold_return: f64,
+    // Même nombre de trades et mêmes distances SL/TP, entrées tirées au hasard
+    pub random_entry_return: f64,
+    pub random_entry_runs: usize,
+    pub excess_return: f64,
+    pub excess_over_random: f64,
+    // Régression des rendements par kline de la stratégie sur ceux du marché ;
+    // alpha = rendement total - beta * rendement buy-and-hold
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Benchmark {
+    pub fn compute(
+        result: &StrategyResult,
+        series: SeriesView,
+        klines: &dyn KlineSource,
+        trades: &[Trade],
+        limits: PortfolioLimits,
+        costs: CostModel,
+        config: BenchmarkConfig,
+    ) -> Self {
+        let strategy_return = total_return(result);
+        let buy_and_hold_return = buy_and_hold_return(series);
+        let random_entry_return =
+            random_entry_return(series, klines, result, trades, limits, costs, config);
+        let equity = equity_curve(series, trades, result.start_money);
+        let beta = beta(&equity, series);
+
+        Benchmark {
+            buy_and_hold_return,
+            random_entry_return,
+            random_entry_runs: config.random_runs,
+            excess_return: strategy_return - buy_and_hold_return,
+            excess_over_random: strategy_return - random_entry_return,
+            alpha: strategy_return - beta * buy_and_hold_return,
+            beta,
+        }
+    }
+}
+
+pub fn buy_and_hold_return(series: SeriesView) -> f64 {
+    match (series.open.first(), series.close.last()) {
+        (Some(open), Some(close)) if *open > 0. => close / open - 1.,
+        _ => 0.,
+    }
+}
+
+// Capital réalisé à la clôture de chaque kline
+pub fn equity_curve(series: SeriesView, trades: &[Trade], start_money: f64) -> Vec<f64> {
+    let mut closed: Vec<&Trade> = trades
+        .iter()
+        .filter(|trade| matches!(trade.status, Status::Closed(_)))
+        .collect();
+    closed.sort_by_key(|trade| trade.close_time);
+
+    let mut money = start_money;
+    let mut next = 0;
+    series
+        .close_time
+        .iter()
+        .map(|close_time| {
+            while next < closed.len() && closed[next].close_time <= *close_time {
+                money += closed[next].realized_pnl();
+                next += 1;
+            }
+            money
+        })
+        .collect()
+}
+
+// Sensibilité des rendements par kline du capital à ceux du prix de clôture
+pub fn beta(equity: &[f64], series: SeriesView) -> f64 {
+    let returns = |values: Vec<f64>| -> Vec<f64> {
+        values
+            .windows(2)
+            .map(|pair| {
+                if pair[0] == 0. {
+                    0.
+                } else {
+                    pair[1] / pair[0] - 1.
+                }
+            })
+            .collect()
+    };
+    let strategy = returns(equity.to_vec());
+    let market = returns(series.close.to_vec());
+    if market.is_empty() || strategy.len() != market.len() {
+        return 0.;
+    }
+
+    let n = market.len() as f64;
+    let strategy_mean = strategy.iter().sum::<f64>() / n;
+    let market_mean = market.iter().sum::<f64>() / n;
+    let mut covariance = 0.;
+    let mut variance = 0.;
+    for (s, m) in strategy.iter().zip(market.iter()) {
+        covariance += (s - strategy_mean) * (m - market_mean);
+        variance += (m - market_mean) * (m - market_mean);
+    }
+    if variance == 0. {
+        0.
+    } else {
+        covariance / variance
+    }
+}
+
+// Rendement moyen d'entrées au prix de clôture de klines tirées au hasard, autant que de
+// trades ouverts par la stratégie, avec les SL/TP de ces trades en proportion du prix d'entrée
+pub fn random_entry_return(
+    series: SeriesView,
+    klines: &dyn KlineSource,
+    result: &StrategyResult,
+    trades: &[Trade],
+    limits: PortfolioLimits,
+    costs: CostModel,
+    config: BenchmarkConfig,
+) -> f64 {
+    let exits: Vec<(f64, f64)> = trades
+        .iter()
+        .filter(|trade| matches!(trade.status, Status::Running | Status::Closed(_)))
+        .filter(|trade| trade.entry_price > 0.)
+        .map(|trade| (trade.sl / trade.entry_price, trade.tp / trade.entry_price))
+        .collect();
+    let (start_money, strategy_params) = (result.start_money, result.strategy_params);
+    if exits.is_empty() || series.is_empty() || config.random_runs == 0 || start_money <= 0. {
+        return 0.;
+    }
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut total = 0.;
+    for _ in 0..config.random_runs {
+        let mut entries: Vec<usize> = (0..exits.len())
+            .map(|_| rng.gen_range(0..series.len()))
+            .collect();
+        entries.sort_unstable();
+        let random_trades: Vec<Trade> = entries
+            .iter()
+            .map(|&index| {
+                let (sl, tp) = exits[rng.gen_range(0..exits.len())];
+                let close = series.close[index];
+                let signal = Signal {
+                    open_time: series.close_time[index],
+                    entry_price: close,
+                    sl: close * sl,
+                    tp: close * tp,
+                };
+                signal.to_trade(klines.kline(index), strategy_params.name)
+            })
+            .collect();
+
+        let mut account = Account::new(start_money, strategy_params.market_type, limits);
+        account.costs = costs;
+        let mut books = [TradeBook::new(random_trades)];
+        for i in 0..series.len() {
+            if !account.process_bars(&mut books, &[Bar::new(series, klines, i)]) {
+                break;
+            }
+        }
+        total += account.money / start_money - 1.;
+    }
+    total / config.random_runs as f64
+}
@@ -0,0 +1,304 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::Arc;
+
+use binance::model::KlineSummary;
+use serde::{Deserialize, Serialize};
+
+use crate::account::*;
+use crate::backtest::*;
+use crate::benchmark::*;
+use crate::confluence::*;
+use crate::error::{Error, Result};
+use crate::filters::*;
+use crate::kline_file::*;
+use crate::metrics::*;
+use crate::param_space::*;
+use crate::patterns::*;
+use crate::series::*;
+use crate::strategies::*;
+use crate::strategies_creator::*;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DataConfig {
+    // Fichier JSON de KlineSummary, tel qu'écrit par tools::write_data_to_file,
+    // ou fichier binaire .bin écrit par kline_file::write_kline_file
+    pub file: String,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub interval: Option<String>,
+}
+
+// Plages balayées pour une stratégie. pattern_size et pattern_range correspondent à
+// klines_repetitions/klines_range pour W et M, trend_size/counter_trend_size pour BullReversal.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StrategyConfig {
+    pub name: StrategyName,
+    pub tp: ParamMultiplier<f64>,
+    pub sl: ParamMultiplier<f64>,
+    pub pattern_size: ParamMultiplier<usize>,
+    pub pattern_range: ParamMultiplier<usize>,
+    pub risk: ParamMultiplier<f64>,
+}
+
+// Stratégie sans balayage, avec ses patterns décrits directement :
+// patterns = [{ type = "W", klines_repetitions = 3, klines_range = 6 }]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FixedStrategyConfig {
+    pub name: StrategyName,
+    pub tp: f64,
+    pub sl: f64,
+    pub risk: f64,
+    pub patterns: Vec<PatternConfig>,
+}
+
+// Entrée unique combinant plusieurs composants :
+// rule = "All", "Any" ou { AtLeast = 2 }, window en klines,
+// components = [{ type = "W", ... }, { type = "EmaTrend", period = 50 }].
+// sweep fait varier les paramètres des composants ("0.klines_range", "1.period")
+// ou le nombre requis par AtLeast ("required").
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfluenceStrategyConfig {
+    pub tp: ParamMultiplier<f64>,
+    pub sl: ParamMultiplier<f64>,
+    pub risk: ParamMultiplier<f64>,
+    pub rule: ConfluenceRule,
+    pub window: ParamMultiplier<usize>,
+    pub components: Vec<PatternConfig>,
+    #[serde(default)]
+    pub sweep: BTreeMap<String, ParamMultiplier<usize>>,
+}
+
+impl ConfluenceStrategyConfig {
+    pub fn create_strategies(
+        &self,
+        start_money: f64,
+        market_type: MarketType,
+    ) -> Result<Vec<Strategy>> {
+        let builder = ConfluenceStrategyBuilder {
+            start_money,
+            market_type,
+            rule: self.rule,
+            components: self.components.clone(),
+        };
+        if let Some(name) = self.sweep.keys().find(|name| !builder.is_sweep_key(name)) {
+            return Err(Error::Config(format!(
+                "unknown confluence sweep parameter {:?}",
+                name
+            )));
+        }
+        let strategies =
+            ConfluenceStrategyBuilder::space(self.tp, self.sl, self.window, self.risk, &self.sweep)
+                .create_strategies(&builder);
+        for strategy in strategies.iter() {
+            validate_strategy(&strategy.1, &strategy.2)?;
+        }
+        Ok(strategies)
+    }
+}
+
+impl FixedStrategyConfig {
+    pub fn to_strategy(&self, start_money: f64, market_type: MarketType) -> Result<Strategy> {
+        let patterns_params: Vec<Arc<dyn PatternParams>> =
+            self.patterns.iter().map(PatternConfig::to_params).collect();
+        let strategy_params = StrategyParams {
+            tp_multiplier: self.tp,
+            sl_multiplier: self.sl,
+            risk_per_trade: self.risk * 0.01,
+            money: start_money,
+            name: self.name,
+            market_type,
+        };
+        validate_strategy(&strategy_params, &patterns_params)?;
+        let func = strategy_func_for(self.name)
+            .ok_or_else(|| Error::InvalidParams(format!("unknown strategy {}", self.name)))?;
+        Ok((func, strategy_params, Arc::new(patterns_params)))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunConfig {
+    pub data: DataConfig,
+    pub start_money: f64,
+    pub market_type: MarketType,
+    #[serde(default)]
+    pub costs: CostModel,
+    #[serde(default)]
+    pub limits: PortfolioLimits,
+    #[serde(default = "default_metric")]
+    pub metric: Metric,
+    #[serde(default = "default_top")]
+    pub top: usize,
+    // Comparaison au buy-and-hold et à des entrées aléatoires, absente si None
+    #[serde(default)]
+    pub benchmark: Option<BenchmarkConfig>,
+    // Filtres de signaux, par exemple sur la tendance d'une unité de temps supérieure
+    #[serde(default)]
+    pub filters: Vec<FilterConfig>,
+    #[serde(default)]
+    pub event_driven: bool,
+    #[serde(default)]
+    pub keep_ledger: bool,
+    #[serde(default)]
+    pub output: Option<String>,
+    // Base SQLite utilisée pour reprendre un balayage interrompu
+    #[serde(default)]
+    pub database: Option<String>,
+    #[serde(default)]
+    pub strategies: Vec<StrategyConfig>,
+    #[serde(default)]
+    pub fixed_strategies: Vec<FixedStrategyConfig>,
+    #[serde(default)]
+    pub confluence_strategies: Vec<ConfluenceStrategyConfig>,
+}
+
+fn default_metric() -> Metric {
+    Metric::FinalMoney
+}
+
+fn default_top() -> usize {
+    10
+}
+
+impl RunConfig {
+    // Format choisi selon l'extension : .json, sinon TOML
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        if path.ends_with(".json") {
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(toml::from_str(&content)?)
+        }
+    }
+
+    pub fn load_klines(&self) -> Result<Vec<MathKLine>> {
+        load_klines_file(&self.data.file)
+    }
+
+    pub fn load_kline_data(&self) -> Result<(Arc<dyn KlineSource>, PriceSeries)> {
+        load_kline_data(&self.data.file)
+    }
+
+    pub fn create_strategies(&self) -> Result<Vec<Strategy>> {
+        let mut strategies = Vec::new();
+        for config in self.strategies.iter() {
+            let creator = match config.name {
+                StrategyName::W => create_w_pattern_strategies,
+                StrategyName::M => create_m_pattern_strategies,
+                StrategyName::BullReversal => create_reversal_pattern_strategies,
+                StrategyName::Confluence => {
+                    return Err(Error::InvalidParams(String::from(
+                        "confluence strategies are swept with confluence_strategies",
+                    )))
+                }
+                StrategyName::None => {
+                    return Err(Error::InvalidParams(String::from(
+                        "strategy None cannot be swept",
+                    )))
+                }
+            };
+            strategies.append(&mut creator(
+                self.start_money,
+                config.tp,
+                config.sl,
+                config.pattern_size,
+                config.pattern_range,
+                config.risk,
+                self.market_type,
+            ));
+        }
+        for config in self.fixed_strategies.iter() {
+            strategies.push(config.to_strategy(self.start_money, self.market_type)?);
+        }
+        for config in self.confluence_strategies.iter() {
+            strategies.append(&mut config.create_strategies(self.start_money, self.market_type)?);
+        }
+        Ok(strategies)
+    }
+}
+
+// Klines et colonnes de prix pour le backtester. Un fichier binaire n'est pas décodé : les
+// colonnes sont lues dans le fichier mappé et les MathKLine construites à la demande.
+pub fn load_kline_data(path: &str) -> Result<(Arc<dyn KlineSource>, PriceSeries)> {
+    let (klines, series): (Arc<dyn KlineSource>, PriceSeries) = if path.ends_with(".bin") {
+        let file = KlineFile::open(path)?;
+        let series = file.to_series(0..file.len());
+        (Arc::new(file), series)
+    } else {
+        let klines = read_json_klines(path)?;
+        let series = PriceSeries::from_klines(&klines);
+        (Arc::new(klines), series)
+    };
+    Backtester::validate_series(series.view())?;
+    Ok((klines, series))
+}
+
+// Les klines sont vérifiées avant d'être backtestées
+pub fn load_klines_file(path: &str) -> Result<Vec<MathKLine>> {
+    let klines = if path.ends_with(".bin") {
+        KlineFile::open(path)?.to_klines()
+    } else {
+        read_json_klines(path)?
+    };
+    Backtester::validate_klines(&klines)?;
+    Ok(klines)
+}
+
+fn read_json_klines(path: &str) -> Result<Vec<MathKLine>> {
+    let klines: Vec<KlineSummary> = serde_json::from_str(&fs::read_to_string(path)?)?;
+    Backtester::to_all_math_kline(klines)
+}
+
+fn parse_field<T: std::str::FromStr>(field: &'static str, value: &str) -> Result<T> {
+    value.parse().map_err(|_| Error::Parse {
+        field,
+        value: value.to_string(),
+    })
+}
+
+// Ligne d'export CSV de data.binance.vision :
+// open_time,open,high,low,close,volume,close_time,quote_volume,count,taker_buy_base,taker_buy_quote,ignore
+pub fn parse_binance_csv_line(line: &str) -> Result<KlineSummary> {
+    let fields: Vec<&str> = line.trim().split(',').collect();
+    if fields.len() < 11 {
+        return Err(Error::InvalidData(format!(
+            "expected at least 11 columns, got {}: {:?}",
+            fields.len(),
+            line
+        )));
+    }
+    Ok(KlineSummary {
+        open_time: parse_field("open_time", fields[0])?,
+        open: fields[1].to_string(),
+        high: fields[2].to_string(),
+        low: fields[3].to_string(),
+        close: fields[4].to_string(),
+        volume: fields[5].to_string(),
+        close_time: parse_field("close_time", fields[6])?,
+        quote_asset_volume: fields[7].to_string(),
+        number_of_trades: parse_field("number_of_trades", fields[8])?,
+        taker_buy_base_asset_volume: fields[9].to_string(),
+        taker_buy_quote_asset_volume: fields[10].to_string(),
+    })
+}
+
+// La première ligne est ignorée si c'est un en-tête
+pub fn import_binance_csv(path: &str) -> Result<Vec<KlineSummary>> {
+    let content = fs::read_to_string(path)?;
+    let mut klines = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let is_header = i == 0
+            && line
+                .split(',')
+                .next()
+                .is_some_and(|field| field.parse::<i64>().is_err());
+        if line.trim().is_empty() || is_header {
+            continue;
+        }
+        let kline = parse_binance_csv_line(line)?;
+        Backtester::kline_summary_to_math_kline(&kline)?;
+        klines.push(kline);
+    }
+    Ok(klines)
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::*;
+use crate::error::{Error, Result};
+use crate::indicators::*;
+use crate::patterns::*;
+use crate::progress::*;
+use crate::series::*;
+use crate::strategies::*;
+
+// Nombre de composants devant confirmer un signal
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ConfluenceRule {
+    All,
+    Any,
+    AtLeast(usize),
+}
+
+impl ConfluenceRule {
+    pub fn required(&self, components: usize) -> usize {
+        match self {
+            ConfluenceRule::All => components,
+            ConfluenceRule::Any => 1,
+            ConfluenceRule::AtLeast(required) => *required,
+        }
+    }
+}
+
+impl fmt::Display for ConfluenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfluenceRule::All => write!(f, "All"),
+            ConfluenceRule::Any => write!(f, "Any"),
+            ConfluenceRule::AtLeast(required) => write!(f, "AtLeast({})", required),
+        }
+    }
+}
+
+fn pattern_finder(name: PatternName) -> Option<TradeFinder> {
+    match name {
+        PatternName::W => Some(find_wpattern_trade),
+        PatternName::M => Some(find_mpattern_trade),
+        PatternName::BullReversal => Some(find_bull_reversal_trade),
+        PatternName::None | PatternName::Confluence | PatternName::EmaTrend => None,
+    }
+}
+
+// patterns_params[0] porte la règle, les suivants sont les composants : des patterns,
+// qui déclenchent les entrées, et des conditions sur indicateurs, évaluées à la kline du signal
+pub fn validate_confluence(patterns_params: &[Arc<dyn PatternParams>]) -> Result<()> {
+    let Some(PatternConfig::Confluence { rule, .. }) =
+        patterns_params.first().map(|p| p.to_config())
+    else {
+        return Err(Error::InvalidParams(String::from(
+            "confluence strategies need a Confluence rule first",
+        )));
+    };
+    let components = &patterns_params[1..];
+    let mut patterns = 0;
+    for component in components {
+        let config = component.to_config();
+        match config.name() {
+            PatternName::EmaTrend => {}
+            name if pattern_finder(name).is_some() => patterns += 1,
+            name => {
+                return Err(Error::InvalidParams(format!(
+                    "{} cannot be a confluence component",
+                    name
+                )))
+            }
+        }
+        if let Some((name, _)) = config.values().into_iter().find(|(_, value)| *value < 1.) {
+            return Err(Error::InvalidParams(format!(
+                "{} of {} must be at least 1",
+                name,
+                config.name()
+            )));
+        }
+    }
+    if patterns == 0 {
+        return Err(Error::InvalidParams(String::from(
+            "confluence strategies need at least one pattern component",
+        )));
+    }
+    let required = rule.required(components.len());
+    if required == 0 || required > components.len() {
+        return Err(Error::InvalidParams(format!(
+            "{} components required out of {}",
+            required,
+            components.len()
+        )));
+    }
+    Ok(())
+}
+
+// Chaque signal d'un pattern est une entrée candidate, gardée si assez de composants vont
+// dans le même sens : patterns ayant signalé dans les window klines précédentes, conditions
+// vraies à la kline du signal. Une seule entrée par kline.
+pub fn create_confluence_trades(
+    klines: &dyn KlineSource,
+    series: SeriesView,
+    progression_tracker: Option<&ProgressTracker>,
+    strategy_params: StrategyParams,
+    patterns_params: Arc<Vec<Arc<dyn PatternParams>>>,
+    potential_only: bool,
+) -> Result<Vec<Trade>> {
+    validate_strategy(&strategy_params, &patterns_params)?;
+    let Some(PatternConfig::Confluence { rule, window }) =
+        patterns_params.first().map(|p| p.to_config())
+    else {
+        return Err(Error::InvalidParams(String::from(
+            "confluence strategies need a Confluence rule first",
+        )));
+    };
+    let components = &patterns_params[1..];
+
+    let mut signals: Vec<Vec<(usize, Signal)>> = Vec::new();
+    let mut trends: Vec<(usize, Vec<f64>)> = Vec::new();
+    for (i, component) in components.iter().enumerate() {
+        if let Some(tracker) = progression_tracker {
+            tracker.check_cancelled()?;
+            tracker.report(i as f32 / components.len() as f32 * 0.5);
+        }
+        let config = component.to_config();
+        match config {
+            PatternConfig::EmaTrend { period } => trends.push((period, ema(series.close, period))),
+            _ => {
+                let finder = pattern_finder(config.name()).ok_or_else(|| {
+                    Error::InvalidParams(format!(
+                        "{} cannot be a confluence component",
+                        config.name()
+                    ))
+                })?;
+                signals.push(find_signals(
+                    series,
+                    None,
+                    strategy_params,
+                    &Arc::new(vec![component.clone()]),
+                    potential_only,
+                    finder,
+                )?);
+            }
+        }
+    }
+
+    let required = rule.required(components.len());
+    let mut triggers: Vec<(usize, &Signal)> = signals
+        .iter()
+        .flat_map(|signal| signal.iter().map(|(index, signal)| (*index, signal)))
+        .collect();
+    // Tri stable : à index égal, l'ordre des composants départage
+    triggers.sort_by_key(|(index, _)| *index);
+
+    let mut trades = Vec::new();
+    let mut last_entry = None;
+    for (index, signal) in triggers {
+        if last_entry == Some(index) {
+            continue;
+        }
+        let long = signal.is_long();
+        let start = index.saturating_sub(window);
+        let patterns = signals
+            .iter()
+            .filter(|signal| {
+                let from = signal.partition_point(|(i, _)| *i < start);
+                signal[from..]
+                    .iter()
+                    .take_while(|(i, _)| *i <= index)
+                    .any(|(_, other)| other.is_long() == long)
+            })
+            .count();
+        let conditions = trends
+            .iter()
+            .filter(|(period, ema)| {
+                index + 1 >= *period
+                    && if long {
+                        series.close[index] > ema[index]
+                    } else {
+                        series.close[index] < ema[index]
+                    }
+            })
+            .count();
+        if patterns + conditions >= required {
+            trades.push(signal.to_trade(klines.kline(index), strategy_params.name));
+            last_entry = Some(index);
+        }
+    }
+    Ok(trades)
+}
@@ -0,0 +1,324 @@
+use std::sync::Arc;
+
+use crate::account::*;
+use crate::backtest::*;
+use crate::filters::*;
+use crate::patterns::*;
+use crate::series::*;
+use crate::strategies::*;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Order {
+    // Ordre limite au prix d'entrée avec SL et TP attachés
+    Bracket { entry_price: f64, sl: f64, tp: f64 },
+    // Ferme les positions ouvertes au prix de clôture et annule les ordres en attente
+    CloseAll,
+}
+
+pub struct AccountState<'a> {
+    pub money: f64,
+    pub available_money: f64,
+    pub open_positions: usize,
+    pub trades: &'a [Trade],
+}
+
+// Une stratégie qui ne voit que les klines déjà clôturées
+pub trait BarStrategy: Send {
+    fn strategy_params(&self) -> StrategyParams;
+    fn patterns_params(&self) -> Arc<Vec<Arc<dyn PatternParams>>>;
+    fn on_bar(&mut self, series: SeriesView, account: &AccountState) -> Vec<Order>;
+}
+
+// Adapte les stratégies à patterns existantes au mode kline par kline
+pub struct PatternBarStrategy {
+    strategy: Strategy,
+    finder: Option<TradeFinder>,
+    window: Option<usize>,
+    cursor: usize,
+    reversal: Option<ReversalScan>,
+}
+
+impl PatternBarStrategy {
+    pub fn new(strategy: Strategy) -> Self {
+        PatternBarStrategy {
+            finder: trade_finder(strategy.1.name),
+            window: Self::pattern_window(&strategy.2),
+            reversal: ReversalScan::new(&strategy.2),
+            strategy,
+            cursor: 0,
+        }
+    }
+
+    // Nombre de klines que le pattern peut lire à partir de son début.
+    // None si la recherche parcourt tout l'historique disponible.
+    fn pattern_window(patterns_params: &Arc<Vec<Arc<dyn PatternParams>>>) -> Option<usize> {
+        let params = patterns_params.first()?;
+        if let Some(params) = params.downcast_ref::<WPatternParams>() {
+            Some(params.klines_range * 2)
+        } else {
+            params
+                .downcast_ref::<MPatternParams>()
+                .map(|params| params.klines_range * 2)
+        }
+    }
+}
+
+impl BarStrategy for PatternBarStrategy {
+    fn strategy_params(&self) -> StrategyParams {
+        self.strategy.1
+    }
+
+    fn patterns_params(&self) -> Arc<Vec<Arc<dyn PatternParams>>> {
+        self.strategy.2.clone()
+    }
+
+    fn on_bar(&mut self, series: SeriesView, _: &AccountState) -> Vec<Order> {
+        let mut orders = Vec::new();
+        let finder = match self.finder {
+            Some(finder) => finder,
+            None => return orders,
+        };
+
+        while self.cursor < series.len() {
+            if let Some(window) = self.window {
+                if self.cursor + window > series.len() {
+                    break;
+                }
+            }
+            if let Some(scan) = &mut self.reversal {
+                if !scan.advance(series) {
+                    break;
+                }
+            }
+            match finder(
+                series.from(self.cursor),
+                self.strategy.1,
+                &self.strategy.2,
+                false,
+            ) {
+                Some((end_index, signal)) => {
+                    self.cursor += end_index;
+                    if let Some(scan) = &mut self.reversal {
+                        scan.restart(self.cursor);
+                    }
+                    orders.push(Order::Bracket {
+                        entry_price: signal.entry_price,
+                        sl: signal.sl,
+                        tp: signal.tp,
+                    });
+                }
+                None if self.window.is_some() => self.cursor += 1,
+                // Sans fenêtre bornée, on attend les prochaines klines
+                None => break,
+            }
+        }
+        orders
+    }
+}
+
+// Recherche incrémentale d'un retournement haussier à partir du curseur, pour ne
+// relire que les nouvelles klines au lieu de tout l'historique à chaque barre
+struct ReversalScan {
+    trend_size: usize,
+    counter_trend_size: usize,
+    trend_found: bool,
+    next: usize,
+    run: usize,
+}
+
+impl ReversalScan {
+    fn new(patterns_params: &Arc<Vec<Arc<dyn PatternParams>>>) -> Option<Self> {
+        let params = patterns_params
+            .first()?
+            .downcast_ref::<ReversalPatternParams>()?;
+        if params.trend_size == 0 || params.counter_trend_size == 0 {
+            return None;
+        }
+        Some(ReversalScan {
+            trend_size: params.trend_size,
+            counter_trend_size: params.counter_trend_size,
+            trend_found: false,
+            next: 0,
+            run: 0,
+        })
+    }
+
+    fn restart(&mut self, cursor: usize) {
+        self.trend_found = false;
+        self.next = cursor;
+        self.run = 0;
+    }
+
+    // true dès que la tendance baissière puis la contre-tendance sont complètes
+    fn advance(&mut self, series: SeriesView) -> bool {
+        while self.next < series.len() {
+            let i = self.next;
+            self.next += 1;
+            if !self.trend_found {
+                self.run = if series.is_down(i) { self.run + 1 } else { 0 };
+                if self.run >= self.trend_size {
+                    self.trend_found = true;
+                    self.run = 0;
+                }
+            } else {
+                self.run = if series.is_up(i) { self.run + 1 } else { 0 };
+                if self.run >= self.counter_trend_size {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+// Le moteur ne garde que la série : seule la kline courante est disponible en entier
+struct CurrentKline<'a> {
+    index: usize,
+    kline: &'a MathKLine,
+}
+
+impl KlineSource for CurrentKline<'_> {
+    fn kline(&self, index: usize) -> MathKLine {
+        assert_eq!(index, self.index, "only the current kline is kept");
+        self.kline.clone()
+    }
+}
+
+pub struct EventEngine {
+    strategy: Box<dyn BarStrategy>,
+    strategy_params: StrategyParams,
+    series: PriceSeries,
+    account: Account,
+    book: TradeBook,
+    signal_filters: Vec<Arc<dyn SignalFilter>>,
+    ruined: bool,
+}
+
+impl EventEngine {
+    pub fn new(strategy: Box<dyn BarStrategy>, limits: PortfolioLimits) -> Self {
+        let strategy_params = strategy.strategy_params();
+        EventEngine {
+            strategy,
+            strategy_params,
+            series: PriceSeries::new(),
+            account: Account::new(strategy_params.money, strategy_params.market_type, limits),
+            book: TradeBook::new(Vec::new()),
+            signal_filters: Vec::new(),
+            ruined: false,
+        }
+    }
+
+    pub fn set_cost_model(&mut self, costs: CostModel) -> &mut Self {
+        self.account.costs = costs;
+        self
+    }
+
+    // Les ordres Bracket refusés par un filtre ne sont ni placés ni retournés
+    pub fn add_signal_filter(&mut self, filter: Arc<dyn SignalFilter>) -> &mut Self {
+        self.signal_filters.push(filter);
+        self
+    }
+
+    // Les ordres passés à la kline précédente sont exécutés sur cette kline,
+    // puis la stratégie reçoit la kline clôturée.
+    pub fn on_kline(&mut self, kline: MathKLine) -> Vec<Order> {
+        if self.ruined {
+            return Vec::new();
+        }
+        self.series.push(&kline);
+        let current = CurrentKline {
+            index: self.series.len() - 1,
+            kline: &kline,
+        };
+        self.on_last_bar(&current)
+    }
+
+    // Rejoue la bougie `index` d'une série déjà chargée, sans décoder de MathKLine
+    // hors des trades
+    pub fn replay_kline(
+        &mut self,
+        series: SeriesView,
+        klines: &dyn KlineSource,
+        index: usize,
+    ) -> Vec<Order> {
+        if self.ruined {
+            return Vec::new();
+        }
+        assert_eq!(index, self.series.len(), "klines must be replayed in order");
+        self.series.push_from(series, index);
+        self.on_last_bar(klines)
+    }
+
+    fn on_last_bar(&mut self, klines: &dyn KlineSource) -> Vec<Order> {
+        let index = self.series.len() - 1;
+        let bar = Bar::new(self.series.view(), klines, index);
+        if !self
+            .account
+            .process_bars(std::slice::from_mut(&mut self.book), &[bar])
+        {
+            self.ruined = true;
+            return Vec::new();
+        }
+
+        let state = AccountState {
+            money: self.account.money,
+            available_money: self.account.available_money(),
+            open_positions: self.account.open_positions,
+            trades: &self.book.trades,
+        };
+        let orders = self.strategy.on_bar(self.series.view(), &state);
+
+        let mut accepted = Vec::with_capacity(orders.len());
+        for order in orders {
+            match order {
+                Order::Bracket {
+                    entry_price,
+                    sl,
+                    tp,
+                } => {
+                    let signal = Signal {
+                        open_time: bar.close_time(),
+                        entry_price,
+                        sl,
+                        tp,
+                    };
+                    let mut trade = signal.to_trade(bar.kline(), self.strategy_params.name);
+                    trade.status = Status::NotTriggered;
+                    if !self
+                        .signal_filters
+                        .iter()
+                        .all(|filter| filter.accepts(&trade))
+                    {
+                        continue;
+                    }
+                    self.book.trades.push(trade);
+                }
+                Order::CloseAll => self.account.close_positions(&mut self.book, bar),
+            }
+            accepted.push(order);
+        }
+        accepted
+    }
+
+    pub fn trades(&self) -> &Vec<Trade> {
+        &self.book.trades
+    }
+
+    pub fn money(&self) -> f64 {
+        self.account.money
+    }
+
+    pub fn result(&self) -> StrategyResult {
+        let mut strategy_params = self.strategy_params;
+        strategy_params.money = self.account.money;
+        let mut result = StrategyResult::from_trades(
+            self.strategy_params.money,
+            strategy_params,
+            &self.strategy.patterns_params(),
+            &self.book.trades,
+            self.account.money_evolution.clone(),
+        );
+        result.ledger = Some(TradeRecord::ledger(&self.book.trades));
+        result
+    }
+}
@@ -0,0 +1,82 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Config(String),
+    Storage(rusqlite::Error),
+    Binance(String),
+    // Valeur d'un champ de kline qui n'est pas un nombre
+    Parse { field: &'static str, value: String },
+    InvalidData(String),
+    InvalidParams(String),
+    InsufficientData { needed: usize, available: usize },
+    Cancelled,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "I/O error: {}", error),
+            Error::Json(error) => write!(f, "JSON error: {}", error),
+            Error::Config(error) => write!(f, "invalid configuration: {}", error),
+            Error::Storage(error) => write!(f, "storage error: {}", error),
+            Error::Binance(error) => write!(f, "Binance error: {}", error),
+            Error::Parse { field, value } => {
+                write!(f, "cannot parse {} from {:?}", field, value)
+            }
+            Error::InvalidData(reason) => write!(f, "invalid data: {}", reason),
+            Error::InvalidParams(reason) => write!(f, "invalid parameters: {}", reason),
+            Error::InsufficientData { needed, available } => write!(
+                f,
+                "insufficient data: {} klines needed, {} available",
+                needed, available
+            ),
+            Error::Cancelled => write!(f, "run cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            Error::Json(error) => Some(error),
+            Error::Storage(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Json(error)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(error: toml::de::Error) -> Self {
+        Error::Config(error.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(error: rusqlite::Error) -> Self {
+        Error::Storage(error)
+    }
+}
+
+impl From<binance::errors::Error> for Error {
+    fn from(error: binance::errors::Error) -> Self {
+        Error::Binance(error.to_string())
+    }
+}
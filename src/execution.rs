@@ -0,0 +1,785 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::{Arc, Mutex};
+
+use binance::account::Account;
+use binance::api::{Binance, Spot, API};
+use binance::general::General;
+use binance::model::Filters;
+use binance::util::build_signed_request;
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::*;
+use crate::error::{Error, Result};
+use crate::strategies::*;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn opposite(&self) -> Side {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Side::Buy => write!(f, "BUY"),
+            Side::Sell => write!(f, "SELL"),
+        }
+    }
+}
+
+// Entrée limite, puis TP et SL en paire OCO du côté opposé une fois l'entrée exécutée
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OrderIntent {
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub entry_price: f64,
+    pub stop_loss: f64,
+    pub take_profit: f64,
+    pub strategy: StrategyName,
+    pub strategy_index: usize,
+    pub market_type: MarketType,
+    pub time: i64,
+}
+
+pub trait ExecutionSink: Send {
+    // Retourne l'identifiant de l'ordre d'entrée attribué par le sink
+    fn submit_entry(&mut self, intent: &OrderIntent) -> Result<String>;
+    // Retourne l'identifiant de la paire OCO
+    fn submit_exits(&mut self, entry_id: &str, intent: &OrderIntent) -> Result<String>;
+    // Annule une entrée pas encore exécutée
+    fn cancel(&mut self, entry_id: &str, intent: &OrderIntent) -> Result<()>;
+    // Annule la paire OCO s'il y en a une et sort de la position au marché
+    fn close(&mut self, exits_id: Option<&str>, intent: &OrderIntent) -> Result<()>;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum OrderState {
+    Created,
+    Submitted,
+    Rejected,
+    EntryFilled,
+    // Paire OCO placée
+    Protected,
+    // Position ouverte sans TP ni SL, à surveiller
+    ExitsRejected,
+    Cancelled,
+    TakeProfitFilled,
+    StopLossFilled,
+    Closed,
+    // L'annulation ou la sortie a échoué : l'ordre ou la position est peut-être encore
+    // ouvert sur l'exchange, à vérifier à la main
+    Failed,
+}
+
+impl OrderState {
+    pub fn can_become(&self, next: OrderState) -> bool {
+        use OrderState::*;
+        matches!(
+            (self, next),
+            (Created, Submitted | Rejected)
+                | (Submitted, EntryFilled | Cancelled | Failed)
+                | (EntryFilled, Protected | ExitsRejected)
+                | (
+                    Protected,
+                    TakeProfitFilled | StopLossFilled | Closed | Failed
+                )
+                | (ExitsRejected, Closed | Failed)
+        )
+    }
+
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            OrderState::Rejected
+                | OrderState::Cancelled
+                | OrderState::TakeProfitFilled
+                | OrderState::StopLossFilled
+                | OrderState::Closed
+        )
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrackedOrder {
+    pub intent: OrderIntent,
+    // Index du trade dans le moteur de la stratégie
+    pub trade: usize,
+    pub entry_id: Option<String>,
+    pub exits_id: Option<String>,
+    // États successifs avec l'heure de la kline qui les a provoqués
+    pub transitions: Vec<(i64, OrderState)>,
+}
+
+impl TrackedOrder {
+    pub fn state(&self) -> OrderState {
+        self.transitions
+            .last()
+            .map_or(OrderState::Created, |(_, state)| *state)
+    }
+}
+
+// Reproduit sur le sink les décisions du compte fictif de chaque stratégie : l'entrée est
+// transmise quand le moteur accepte le trade, exécutée quand le compte ouvre la position,
+// annulée quand il l'écarte, et la position est fermée au marché quand il la remplace.
+pub struct ExecutionRouter {
+    sink: Box<dyn ExecutionSink>,
+    symbol: String,
+    // Capital sur lequel est calculée la quantité, selon le risque par trade de la stratégie
+    capital: f64,
+    // Seuls les ordres de cette stratégie sont transmis si elle est définie
+    strategy: Option<usize>,
+    orders: Vec<TrackedOrder>,
+    // Nombre de trades déjà vus par stratégie
+    seen: BTreeMap<usize, usize>,
+    // Index des ordres non terminés de chaque stratégie
+    active: BTreeMap<usize, Vec<usize>>,
+}
+
+impl ExecutionRouter {
+    pub fn new(sink: Box<dyn ExecutionSink>, symbol: &str, capital: f64) -> Self {
+        ExecutionRouter {
+            sink,
+            symbol: symbol.to_string(),
+            capital,
+            strategy: None,
+            orders: Vec::new(),
+            seen: BTreeMap::new(),
+            active: BTreeMap::new(),
+        }
+    }
+
+    pub fn set_strategy(&mut self, strategy: usize) -> &mut Self {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    pub fn orders(&self) -> &Vec<TrackedOrder> {
+        &self.orders
+    }
+
+    pub fn intent(
+        &self,
+        strategy: usize,
+        strategy_params: StrategyParams,
+        trade: &Trade,
+    ) -> Option<OrderIntent> {
+        let risk = (trade.entry_price - trade.sl).abs();
+        if risk == 0. {
+            return None;
+        }
+        Some(OrderIntent {
+            symbol: self.symbol.clone(),
+            side: if trade.is_long() {
+                Side::Buy
+            } else {
+                Side::Sell
+            },
+            quantity: self.capital * strategy_params.risk_per_trade / risk,
+            entry_price: trade.entry_price,
+            stop_loss: trade.sl,
+            take_profit: trade.tp,
+            strategy: strategy_params.name,
+            strategy_index: strategy,
+            market_type: strategy_params.market_type,
+            time: trade.open_time,
+        })
+    }
+
+    // Reçoit les trades du moteur de la stratégie après chaque kline clôturée à time.
+    // Les refus du sink sont enregistrés dans l'état de l'ordre, seules les erreurs
+    // de suivi sont retournées.
+    pub fn on_trades(
+        &mut self,
+        strategy: usize,
+        strategy_params: StrategyParams,
+        trades: &[Trade],
+        time: i64,
+    ) -> Result<()> {
+        if self.strategy.is_some_and(|only| only != strategy) {
+            return Ok(());
+        }
+        let seen = self.seen.insert(strategy, trades.len()).unwrap_or(0);
+        let mut active = self.active.remove(&strategy).unwrap_or_default();
+        for (index, trade) in trades.iter().enumerate().skip(seen) {
+            if trade.status != Status::NotTriggered {
+                continue;
+            }
+            if let Some(intent) = self.intent(strategy, strategy_params, trade) {
+                active.push(self.submit(intent, index)?);
+            }
+        }
+        for index in active.iter() {
+            let status = trades[self.orders[*index].trade].status;
+            self.follow(*index, status, time)?;
+        }
+        active.retain(|index| !self.orders[*index].state().is_final());
+        self.active.insert(strategy, active);
+        Ok(())
+    }
+
+    // Retourne l'index de l'ordre suivi
+    fn submit(&mut self, intent: OrderIntent, trade: usize) -> Result<usize> {
+        let index = self.orders.len();
+        let time = intent.time;
+        self.orders.push(TrackedOrder {
+            intent,
+            trade,
+            entry_id: None,
+            exits_id: None,
+            transitions: Vec::new(),
+        });
+        match self.sink.submit_entry(&self.orders[index].intent) {
+            Ok(id) => {
+                self.orders[index].entry_id = Some(id);
+                self.transition(index, time, OrderState::Submitted)?;
+            }
+            Err(error) => {
+                log::warn!("Entry order rejected: {}", error);
+                self.transition(index, time, OrderState::Rejected)?;
+            }
+        }
+        Ok(index)
+    }
+
+    // Aligne l'ordre sur le statut du trade correspondant dans le compte fictif
+    fn follow(&mut self, index: usize, status: Status, time: i64) -> Result<()> {
+        if self.orders[index].state() == OrderState::Submitted {
+            match status {
+                Status::Skipped => return self.cancel(index, time),
+                Status::Running | Status::Closed(_) => self.fill_entry(index, time)?,
+                Status::NotOpened | Status::NotTriggered => return Ok(()),
+            }
+        }
+        let Status::Closed(result) = status else {
+            return Ok(());
+        };
+        match (self.orders[index].state(), result) {
+            (OrderState::Protected, TradeResult::Win) => {
+                self.transition(index, time, OrderState::TakeProfitFilled)
+            }
+            // Le SL est supposé touché en premier quand la kline couvre les deux
+            (OrderState::Protected, TradeResult::Lost | TradeResult::Unknown) => {
+                self.transition(index, time, OrderState::StopLossFilled)
+            }
+            // Position remplacée ou fermée par la stratégie, ou sans TP ni SL sur l'exchange
+            (OrderState::Protected | OrderState::ExitsRejected, _) => self.close(index, time),
+            _ => Ok(()),
+        }
+    }
+
+    fn fill_entry(&mut self, index: usize, time: i64) -> Result<()> {
+        self.transition(index, time, OrderState::EntryFilled)?;
+        let order = &self.orders[index];
+        let entry_id = order.entry_id.clone().unwrap_or_default();
+        match self.sink.submit_exits(&entry_id, &order.intent) {
+            Ok(id) => {
+                self.orders[index].exits_id = Some(id);
+                self.transition(index, time, OrderState::Protected)
+            }
+            Err(error) => {
+                log::error!("Exit orders rejected, position left open: {}", error);
+                self.transition(index, time, OrderState::ExitsRejected)
+            }
+        }
+    }
+
+    fn cancel(&mut self, index: usize, time: i64) -> Result<()> {
+        let order = &self.orders[index];
+        let entry_id = order.entry_id.clone().unwrap_or_default();
+        match self.sink.cancel(&entry_id, &order.intent) {
+            Ok(()) => self.transition(index, time, OrderState::Cancelled),
+            Err(error) => {
+                log::error!("Cancellation of order {} failed: {}", entry_id, error);
+                self.transition(index, time, OrderState::Failed)
+            }
+        }
+    }
+
+    fn close(&mut self, index: usize, time: i64) -> Result<()> {
+        let order = &self.orders[index];
+        match self.sink.close(order.exits_id.as_deref(), &order.intent) {
+            Ok(()) => self.transition(index, time, OrderState::Closed),
+            Err(error) => {
+                log::error!(
+                    "Closing of the position of order {} failed: {}",
+                    index,
+                    error
+                );
+                self.transition(index, time, OrderState::Failed)
+            }
+        }
+    }
+
+    fn transition(&mut self, index: usize, time: i64, next: OrderState) -> Result<()> {
+        let order = &mut self.orders[index];
+        let state = order.state();
+        if !state.can_become(next) {
+            return Err(Error::InvalidData(format!(
+                "order {} cannot go from {:?} to {:?}",
+                index, state, next
+            )));
+        }
+        order.transitions.push((time, next));
+        Ok(())
+    }
+}
+
+// N'envoie rien, journalise les ordres
+#[derive(Default)]
+pub struct DryRunSink {
+    next_id: usize,
+}
+
+impl DryRunSink {
+    pub fn new() -> Self {
+        DryRunSink::default()
+    }
+
+    fn id(&mut self) -> String {
+        self.next_id += 1;
+        format!("dry-{}", self.next_id)
+    }
+}
+
+impl ExecutionSink for DryRunSink {
+    fn submit_entry(&mut self, intent: &OrderIntent) -> Result<String> {
+        let id = self.id();
+        log::info!(
+            "[dry run] {} entry {} {} {} at {}",
+            id,
+            intent.side,
+            intent.quantity,
+            intent.symbol,
+            intent.entry_price
+        );
+        Ok(id)
+    }
+
+    fn submit_exits(&mut self, entry_id: &str, intent: &OrderIntent) -> Result<String> {
+        let id = self.id();
+        log::info!(
+            "[dry run] {} OCO {} for {}: TP {} SL {}",
+            id,
+            intent.side.opposite(),
+            entry_id,
+            intent.take_profit,
+            intent.stop_loss
+        );
+        Ok(id)
+    }
+
+    fn cancel(&mut self, entry_id: &str, _: &OrderIntent) -> Result<()> {
+        log::info!("[dry run] cancel {}", entry_id);
+        Ok(())
+    }
+
+    fn close(&mut self, exits_id: Option<&str>, intent: &OrderIntent) -> Result<()> {
+        if let Some(exits_id) = exits_id {
+            log::info!("[dry run] cancel {}", exits_id);
+        }
+        log::info!(
+            "[dry run] {} {} {} at market",
+            intent.side.opposite(),
+            intent.quantity,
+            intent.symbol
+        );
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action")]
+pub enum SinkRecord {
+    Entry {
+        id: String,
+        intent: OrderIntent,
+    },
+    Exits {
+        id: String,
+        entry_id: String,
+        intent: OrderIntent,
+    },
+    Cancel {
+        entry_id: String,
+        intent: OrderIntent,
+    },
+    Close {
+        exits_id: Option<String>,
+        intent: OrderIntent,
+    },
+}
+
+// Une ligne JSON par ordre, écrite immédiatement
+pub struct JsonLinesSink {
+    writer: BufWriter<File>,
+    next_id: usize,
+}
+
+impl JsonLinesSink {
+    pub fn create(path: &str) -> Result<Self> {
+        Ok(JsonLinesSink {
+            writer: BufWriter::new(File::create(path)?),
+            next_id: 0,
+        })
+    }
+
+    fn id(&mut self) -> String {
+        self.next_id += 1;
+        self.next_id.to_string()
+    }
+
+    fn write(&mut self, record: &SinkRecord) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl ExecutionSink for JsonLinesSink {
+    fn submit_entry(&mut self, intent: &OrderIntent) -> Result<String> {
+        let id = self.id();
+        self.write(&SinkRecord::Entry {
+            id: id.clone(),
+            intent: intent.clone(),
+        })?;
+        Ok(id)
+    }
+
+    fn submit_exits(&mut self, entry_id: &str, intent: &OrderIntent) -> Result<String> {
+        let id = self.id();
+        self.write(&SinkRecord::Exits {
+            id: id.clone(),
+            entry_id: entry_id.to_string(),
+            intent: intent.clone(),
+        })?;
+        Ok(id)
+    }
+
+    fn cancel(&mut self, entry_id: &str, intent: &OrderIntent) -> Result<()> {
+        self.write(&SinkRecord::Cancel {
+            entry_id: entry_id.to_string(),
+            intent: intent.clone(),
+        })
+    }
+
+    fn close(&mut self, exits_id: Option<&str>, intent: &OrderIntent) -> Result<()> {
+        self.write(&SinkRecord::Close {
+            exits_id: exits_id.map(String::from),
+            intent: intent.clone(),
+        })
+    }
+}
+
+// Pas de quantité des ordres limites (filtre LOT_SIZE) et au marché (filtre MARKET_LOT_SIZE)
+// et pas de prix (filtre PRICE_FILTER) d'un symbole, avec le nombre de décimales à envoyer
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SymbolSteps {
+    pub step_size: f64,
+    pub quantity_decimals: usize,
+    pub market_step_size: f64,
+    pub market_quantity_decimals: usize,
+    pub tick_size: f64,
+    pub price_decimals: usize,
+}
+
+impl SymbolSteps {
+    pub fn from_filters(symbol: &str, filters: &[Filters]) -> Result<Self> {
+        let mut lot_size = None;
+        let mut market_lot_size = None;
+        let mut price_filter = None;
+        for filter in filters {
+            match filter {
+                Filters::LotSize { step_size, .. } => {
+                    lot_size = Some(parse_step("step_size", step_size)?)
+                }
+                Filters::MarketLotSize { step_size, .. } => {
+                    market_lot_size = Some(step_size.as_str())
+                }
+                Filters::PriceFilter { tick_size, .. } => {
+                    price_filter = Some(parse_step("tick_size", tick_size)?)
+                }
+                _ => {}
+            }
+        }
+        let ((step_size, quantity_decimals), (tick_size, price_decimals)) =
+            lot_size.zip(price_filter).ok_or_else(|| {
+                Error::Binance(format!("missing LOT_SIZE or PRICE_FILTER for {}", symbol))
+            })?;
+        // Binance envoie un pas nul quand les ordres au marché n'ont pas de pas propre
+        let (market_step_size, market_quantity_decimals) = match market_lot_size {
+            Some(step) if step.parse::<f64>().is_ok_and(|step| step > 0.) => {
+                parse_step("market_step_size", step)?
+            }
+            _ => (step_size, quantity_decimals),
+        };
+        Ok(SymbolSteps {
+            step_size,
+            quantity_decimals,
+            market_step_size,
+            market_quantity_decimals,
+            tick_size,
+            price_decimals,
+        })
+    }
+
+    // Arrondie vers le bas pour ne pas dépasser le risque prévu
+    pub fn quantity(&self, quantity: f64) -> f64 {
+        round_to(
+            (quantity / self.step_size).floor() * self.step_size,
+            self.quantity_decimals,
+        )
+    }
+
+    // Arrondie vers le bas pour ne pas vendre plus que la position
+    pub fn market_quantity(&self, quantity: f64) -> f64 {
+        round_to(
+            (quantity / self.market_step_size).floor() * self.market_step_size,
+            self.market_quantity_decimals,
+        )
+    }
+
+    pub fn price(&self, price: f64) -> f64 {
+        round_to(
+            (price / self.tick_size).round() * self.tick_size,
+            self.price_decimals,
+        )
+    }
+}
+
+// Pas donné par Binance ("0.00100000") et son nombre de décimales significatives
+fn parse_step(field: &'static str, value: &str) -> Result<(f64, usize)> {
+    let step: f64 = value.parse().map_err(|_| Error::Parse {
+        field,
+        value: value.to_string(),
+    })?;
+    if step <= 0. {
+        return Err(Error::Binance(format!("invalid {} {}", field, value)));
+    }
+    let decimals = value
+        .split_once('.')
+        .map_or(0, |(_, decimals)| decimals.trim_end_matches('0').len());
+    Ok((step, decimals))
+}
+
+// Retire les erreurs d'arrondi des flottants, pour que to_string donne au plus decimals décimales
+fn round_to(value: f64, decimals: usize) -> f64 {
+    format!("{:.*}", decimals, value).parse().unwrap_or(value)
+}
+
+// Ordres spot passés sur Binance : entrée limite GTC, puis paire OCO
+// (TP limite, SL stop-limit au prix du SL). Quantités et prix sont arrondis
+// aux pas du symbole, chargés à la création. Sans marge, seules les entrées à l'achat
+// des stratégies spot sont acceptées.
+pub struct BinanceSink {
+    account: Account,
+    steps: SymbolSteps,
+}
+
+impl BinanceSink {
+    pub fn new(api_key: String, secret_key: String, symbol: &str) -> Result<Self> {
+        let general: General = Binance::new(None, None);
+        let info = general.get_symbol_info(symbol)?;
+        Ok(BinanceSink {
+            account: Binance::new(Some(api_key), Some(secret_key)),
+            steps: SymbolSteps::from_filters(symbol, &info.filters)?,
+        })
+    }
+
+    fn quantity(&self, intent: &OrderIntent) -> Result<f64> {
+        check_quantity(
+            intent.quantity,
+            self.steps.quantity(intent.quantity),
+            self.steps.step_size,
+        )
+    }
+
+    fn market_quantity(&self, intent: &OrderIntent) -> Result<f64> {
+        check_quantity(
+            intent.quantity,
+            self.steps.market_quantity(intent.quantity),
+            self.steps.market_step_size,
+        )
+    }
+}
+
+fn check_quantity(requested: f64, quantity: f64, step_size: f64) -> Result<f64> {
+    if quantity <= 0. {
+        return Err(Error::InvalidParams(format!(
+            "quantity {} is below the lot step {}",
+            requested, step_size
+        )));
+    }
+    Ok(quantity)
+}
+
+impl ExecutionSink for BinanceSink {
+    fn submit_entry(&mut self, intent: &OrderIntent) -> Result<String> {
+        if intent.market_type != MarketType::Spot {
+            return Err(Error::InvalidParams(format!(
+                "{:?} strategies cannot be traded on the Binance spot market",
+                intent.market_type
+            )));
+        }
+        if intent.side == Side::Sell {
+            return Err(Error::InvalidParams(String::from(
+                "short entries need margin and cannot be traded on the Binance spot market",
+            )));
+        }
+        let quantity = self.quantity(intent)?;
+        let price = self.steps.price(intent.entry_price);
+        let transaction = self.account.limit_buy(&intent.symbol, quantity, price)?;
+        Ok(transaction.order_id.to_string())
+    }
+
+    fn submit_exits(&mut self, _: &str, intent: &OrderIntent) -> Result<String> {
+        let stop_loss = self.steps.price(intent.stop_loss).to_string();
+        let mut parameters = BTreeMap::new();
+        parameters.insert(String::from("symbol"), intent.symbol.clone());
+        parameters.insert(String::from("side"), intent.side.opposite().to_string());
+        parameters.insert(String::from("quantity"), self.quantity(intent)?.to_string());
+        parameters.insert(
+            String::from("price"),
+            self.steps.price(intent.take_profit).to_string(),
+        );
+        parameters.insert(String::from("stopPrice"), stop_loss.clone());
+        parameters.insert(String::from("stopLimitPrice"), stop_loss);
+        parameters.insert(String::from("stopLimitTimeInForce"), String::from("GTC"));
+        let request = build_signed_request(parameters, self.account.recv_window)?;
+        let response: serde_json::Value = self
+            .account
+            .client
+            .post_signed(API::Spot(Spot::Oco), request)?;
+        response
+            .get("orderListId")
+            .map(|id| id.to_string())
+            .ok_or_else(|| Error::Binance(format!("unexpected OCO response {}", response)))
+    }
+
+    fn cancel(&mut self, entry_id: &str, intent: &OrderIntent) -> Result<()> {
+        let order_id = entry_id.parse().map_err(|_| Error::Parse {
+            field: "order_id",
+            value: entry_id.to_string(),
+        })?;
+        self.account.cancel_order(&intent.symbol, order_id)?;
+        Ok(())
+    }
+
+    fn close(&mut self, exits_id: Option<&str>, intent: &OrderIntent) -> Result<()> {
+        // Sans paire OCO (refusée à l'envoi), il n'y a que la sortie au marché
+        if let Some(exits_id) = exits_id {
+            let mut parameters = BTreeMap::new();
+            parameters.insert(String::from("symbol"), intent.symbol.clone());
+            parameters.insert(String::from("orderListId"), exits_id.to_string());
+            let request = build_signed_request(parameters, self.account.recv_window)?;
+            let _: serde_json::Value = self
+                .account
+                .client
+                .delete_signed(API::Spot(Spot::OrderList), Some(request))?;
+        }
+        let quantity = self.market_quantity(intent)?;
+        match intent.side.opposite() {
+            Side::Buy => self.account.market_buy(&intent.symbol, quantity)?,
+            Side::Sell => self.account.market_sell(&intent.symbol, quantity)?,
+        };
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum MockOrderKind {
+    Entry,
+    Exits,
+    Cancel,
+    Close,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MockOrder {
+    pub id: String,
+    pub kind: MockOrderKind,
+    pub intent: OrderIntent,
+}
+
+#[derive(Default)]
+struct MockState {
+    orders: Vec<MockOrder>,
+    reject: bool,
+}
+
+// Exchange local qui enregistre les ordres reçus. Les clones partagent le même état,
+// ce qui permet d'inspecter les ordres d'un sink confié à un ExecutionRouter.
+#[derive(Clone, Default)]
+pub struct MockExchange {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockExchange {
+    pub fn new() -> Self {
+        MockExchange::default()
+    }
+
+    // Refuse les ordres suivants tant que reject est vrai
+    pub fn set_reject(&self, reject: bool) -> &Self {
+        self.state.lock().unwrap().reject = reject;
+        self
+    }
+
+    pub fn orders(&self) -> Vec<MockOrder> {
+        self.state.lock().unwrap().orders.clone()
+    }
+
+    fn record(&self, id: &str, kind: MockOrderKind, intent: &OrderIntent) -> Result<String> {
+        let mut state = self.state.lock().unwrap();
+        if state.reject {
+            return Err(Error::Binance(format!(
+                "{:?} order rejected by the mock exchange",
+                kind
+            )));
+        }
+        let id = if id.is_empty() {
+            format!("mock-{}", state.orders.len() + 1)
+        } else {
+            id.to_string()
+        };
+        state.orders.push(MockOrder {
+            id: id.clone(),
+            kind,
+            intent: intent.clone(),
+        });
+        Ok(id)
+    }
+}
+
+impl ExecutionSink for MockExchange {
+    fn submit_entry(&mut self, intent: &OrderIntent) -> Result<String> {
+        self.record("", MockOrderKind::Entry, intent)
+    }
+
+    fn submit_exits(&mut self, _: &str, intent: &OrderIntent) -> Result<String> {
+        self.record("", MockOrderKind::Exits, intent)
+    }
+
+    fn cancel(&mut self, entry_id: &str, intent: &OrderIntent) -> Result<()> {
+        self.record(entry_id, MockOrderKind::Cancel, intent)?;
+        Ok(())
+    }
+
+    fn close(&mut self, exits_id: Option<&str>, intent: &OrderIntent) -> Result<()> {
+        self.record(exits_id.unwrap_or_default(), MockOrderKind::Close, intent)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::*;
+use crate::error::{Error, Result};
+use crate::indicators::*;
+use crate::series::*;
+use crate::timeframe::*;
+
+// Appliqué à chaque trade généré par une stratégie, avant sa résolution.
+// trade.open_time est la clôture de la kline du signal : un filtre ne doit rien lire après.
+pub trait SignalFilter: Send + Sync {
+    fn accepts(&self, trade: &Trade) -> bool;
+    // Description stable, intégrée au hash des paramètres de la base de résultats
+    fn describe(&self) -> String;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum SlopeDirection {
+    Positive,
+    Negative,
+    // Pente positive pour un long, négative pour un short
+    #[default]
+    WithTrade,
+}
+
+// Garde les signaux dont la pente de l'EMA de l'unité de temps supérieure, sur la dernière
+// kline clôturée, va dans la direction demandée
+pub struct EmaSlopeFilter {
+    timeframe: AlignedTimeframe,
+    ema: Vec<f64>,
+    period: usize,
+    direction: SlopeDirection,
+}
+
+impl EmaSlopeFilter {
+    pub fn new(timeframe: AlignedTimeframe, period: usize, direction: SlopeDirection) -> Self {
+        EmaSlopeFilter {
+            ema: ema(&timeframe.series.close, period),
+            timeframe,
+            period,
+            direction,
+        }
+    }
+}
+
+impl SignalFilter for EmaSlopeFilter {
+    fn accepts(&self, trade: &Trade) -> bool {
+        let Some(index) = self.timeframe.last_closed(trade.open_time) else {
+            return false;
+        };
+        // Pas de décision pendant le préchauffage de l'EMA
+        if index + 1 < self.period {
+            return false;
+        }
+        let Some(slope) = slope(&self.ema, index) else {
+            return false;
+        };
+        match self.direction {
+            SlopeDirection::Positive => slope > 0.,
+            SlopeDirection::Negative => slope < 0.,
+            SlopeDirection::WithTrade if trade.is_long() => slope > 0.,
+            SlopeDirection::WithTrade => slope < 0.,
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "ema_slope interval={} period={} direction={:?}",
+            self.timeframe.interval, self.period, self.direction
+        )
+    }
+}
+
+// Forme sérialisable des filtres : { type = "EmaSlope", interval = "4h", period = 20 }
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum FilterConfig {
+    EmaSlope {
+        interval: String,
+        period: usize,
+        #[serde(default)]
+        direction: SlopeDirection,
+    },
+}
+
+impl FilterConfig {
+    // L'unité de temps supérieure est reconstruite à partir des klines de base
+    pub fn build(&self, series: SeriesView) -> Result<Arc<dyn SignalFilter>> {
+        match self {
+            FilterConfig::EmaSlope {
+                interval,
+                period,
+                direction,
+            } => {
+                let millis = interval_millis(interval)
+                    .ok_or_else(|| Error::Config(format!("unknown interval {}", interval)))?;
+                if *period == 0 {
+                    return Err(Error::Config(String::from("EMA period must be at least 1")));
+                }
+                Ok(Arc::new(EmaSlopeFilter::new(
+                    AlignedTimeframe::from_base(series, millis),
+                    *period,
+                    *direction,
+                )))
+            }
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::account::*;
+use crate::backtest::*;
+use crate::error::{Error, Result};
+use crate::metrics::*;
+use crate::param_space::*;
+use crate::patterns::*;
+use crate::sampling::*;
+use crate::strategies::*;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GeneticConfig {
+    pub population_size: usize,
+    pub generations: usize,
+    pub crossover_rate: f64,
+    pub mutation_rate: f64,
+    // Nombre de meilleurs individus recopiés tels quels dans la génération suivante
+    pub elitism: usize,
+    pub tournament_size: usize,
+    pub fitness: Metric,
+    pub seed: u64,
+    // Arrêt si le meilleur score ne progresse plus pendant ce nombre de générations
+    pub patience: Option<usize>,
+    pub portfolio_limits: PortfolioLimits,
+}
+
+impl Default for GeneticConfig {
+    fn default() -> Self {
+        GeneticConfig {
+            population_size: 50,
+            generations: 30,
+            crossover_rate: 0.8,
+            mutation_rate: 0.1,
+            elitism: 2,
+            tournament_size: 3,
+            fitness: Metric::FinalMoney,
+            seed: 0,
+            patience: Some(5),
+            portfolio_limits: PortfolioLimits::unlimited(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GenerationReport {
+    pub generation: usize,
+    pub best_fitness: f64,
+    pub mean_fitness: f64,
+    pub best_params: ParamSet,
+    pub best: Option<StrategyResult>,
+}
+
+#[derive(Clone)]
+pub struct GeneticReport {
+    pub generations: Vec<GenerationReport>,
+    pub best_params: ParamSet,
+    pub best_strategy: Strategy,
+    pub best_result: StrategyResult,
+    pub evaluations: usize,
+    pub stopped_early: bool,
+}
+
+// Chaque gène est une coordonnée dans [0, 1) décodée par l'espace de paramètres
+type Genome = Vec<f64>;
+
+const MUTATION_SPREAD: f64 = 0.2;
+
+pub struct GeneticOptimizer {
+    klines_data: Arc<Vec<MathKLine>>,
+    space: ParamSpace,
+    builder: Box<dyn StrategyBuilder>,
+    config: GeneticConfig,
+    cache: HashMap<String, (f64, Option<StrategyResult>)>,
+    rng: StdRng,
+}
+
+impl GeneticOptimizer {
+    pub fn new(
+        klines_data: Arc<Vec<MathKLine>>,
+        space: ParamSpace,
+        builder: Box<dyn StrategyBuilder>,
+        config: GeneticConfig,
+    ) -> Self {
+        GeneticOptimizer {
+            klines_data,
+            space,
+            builder,
+            config,
+            cache: HashMap::new(),
+            rng: StdRng::seed_from_u64(config.seed),
+        }
+    }
+
+    // run() refuse les espaces dont une dimension est vide, le décodage ne peut pas échouer
+    fn decode(&self, genome: &Genome) -> ParamSet {
+        self.space
+            .from_unit(genome, SamplingMethod::Uniform)
+            .unwrap_or_default()
+    }
+
+    // Évalue les combinaisons qui ne sont pas encore en cache en un seul passage du backtester.
+    // Les combinaisons qui ne respectent pas les contraintes ont un score de -inf.
+    fn evaluate(&mut self, population: &[Genome]) -> Result<Vec<(f64, Option<StrategyResult>)>> {
+        let params: Vec<ParamSet> = population
+            .iter()
+            .map(|genome| self.decode(genome))
+            .collect();
+        let mut missing: Vec<(String, Strategy)> = Vec::new();
+        for set in params.iter() {
+            let key = set.key();
+            if self.cache.contains_key(&key) || missing.iter().any(|(other, _)| *other == key) {
+                continue;
+            }
+            let strategy = self.builder.build(set).filter(|strategy| {
+                self.space.is_valid(set) && validate_strategy(&strategy.1, &strategy.2).is_ok()
+            });
+            match strategy {
+                Some(strategy) => missing.push((key, strategy)),
+                None => {
+                    self.cache.insert(key, (f64::NEG_INFINITY, None));
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            let mut strategies: Vec<Strategy> = missing
+                .iter()
+                .map(|(_, strategy)| strategy.clone())
+                .collect();
+            let results = Backtester::new(self.klines_data.clone(), None, None, false)
+                .set_portfolio_limits(self.config.portfolio_limits)
+                .add_strategies(&mut strategies)
+                .start()?
+                .get_results();
+            for ((key, _), result) in missing.into_iter().zip(results) {
+                let fitness = self.config.fitness.score(&result);
+                self.cache.insert(key, (fitness, Some(result)));
+            }
+        }
+
+        Ok(params
+            .iter()
+            .map(|set| self.cache[&set.key()].clone())
+            .collect())
+    }
+
+    fn tournament<'a>(&mut self, population: &'a [Genome], fitness: &[f64]) -> &'a Genome {
+        let mut best = self.rng.gen_range(0..population.len());
+        for _ in 1..self.config.tournament_size.max(1) {
+            let challenger = self.rng.gen_range(0..population.len());
+            if fitness[challenger] > fitness[best] {
+                best = challenger;
+            }
+        }
+        &population[best]
+    }
+
+    fn crossover(&mut self, a: &Genome, b: &Genome) -> Genome {
+        if !self.rng.gen_bool(self.config.crossover_rate.clamp(0., 1.)) {
+            return a.clone();
+        }
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| if self.rng.gen_bool(0.5) { *x } else { *y })
+            .collect()
+    }
+
+    fn mutate(&mut self, genome: &mut Genome) {
+        for gene in genome.iter_mut() {
+            if self.rng.gen_bool(self.config.mutation_rate.clamp(0., 1.)) {
+                let delta = self.rng.gen_range(-MUTATION_SPREAD..=MUTATION_SPREAD);
+                *gene = (*gene + delta).clamp(0., 1. - f64::EPSILON);
+            }
+        }
+    }
+
+    pub fn run(&mut self) -> Result<GeneticReport> {
+        if self.space.has_empty_dimension() {
+            return Err(Error::InvalidParams(String::from(
+                "the parameter space has an empty dimension",
+            )));
+        }
+        let size = self.config.population_size.max(2);
+        let mut population: Vec<Genome> = unit_samples(
+            size,
+            self.space.dimensions.len(),
+            SamplingMethod::LatinHypercube,
+            &mut self.rng,
+        );
+
+        let mut generations = Vec::new();
+        let mut best: Option<(f64, ParamSet, StrategyResult)> = None;
+        let mut stale = 0;
+        let mut stopped_early = false;
+
+        for generation in 0..self.config.generations {
+            let evaluated = self.evaluate(&population)?;
+            let fitness: Vec<f64> = evaluated.iter().map(|(fitness, _)| *fitness).collect();
+
+            let mut order: Vec<usize> = (0..population.len()).collect();
+            order.sort_by(|a, b| fitness[*b].total_cmp(&fitness[*a]));
+            let generation_best = order[0];
+
+            let finite: Vec<f64> = fitness.iter().copied().filter(|f| f.is_finite()).collect();
+            generations.push(GenerationReport {
+                generation,
+                best_fitness: fitness[generation_best],
+                mean_fitness: if finite.is_empty() {
+                    0.
+                } else {
+                    finite.iter().sum::<f64>() / finite.len() as f64
+                },
+                best_params: self.decode(&population[generation_best]),
+                best: evaluated[generation_best].1.clone(),
+            });
+
+            let improved = best
+                .as_ref()
+                .is_none_or(|(best_fitness, _, _)| fitness[generation_best] > *best_fitness);
+            match &evaluated[generation_best].1 {
+                Some(result) if improved => {
+                    best = Some((
+                        fitness[generation_best],
+                        self.decode(&population[generation_best]),
+                        result.clone(),
+                    ));
+                    stale = 0;
+                }
+                _ => stale += 1,
+            }
+            if let Some(patience) = self.config.patience {
+                if stale >= patience {
+                    stopped_early = true;
+                    break;
+                }
+            }
+
+            let mut next: Vec<Genome> = order
+                .iter()
+                .take(self.config.elitism.min(size))
+                .map(|i| population[*i].clone())
+                .collect();
+            while next.len() < size {
+                let a = self.tournament(&population, &fitness).clone();
+                let b = self.tournament(&population, &fitness).clone();
+                let mut child = self.crossover(&a, &b);
+                self.mutate(&mut child);
+                next.push(child);
+            }
+            population = next;
+        }
+
+        let (_, best_params, best_result) = best.ok_or_else(|| {
+            Error::InvalidParams(String::from("no valid parameter set in the search space"))
+        })?;
+        let best_strategy = self.builder.build(&best_params).ok_or_else(|| {
+            Error::InvalidParams(String::from("the best parameter set cannot be rebuilt"))
+        })?;
+        Ok(GeneticReport {
+            generations,
+            best_strategy,
+            best_params,
+            best_result,
+            evaluations: self.cache.len(),
+            stopped_early,
+        })
+    }
+}
@@ -0,0 +1,42 @@
+// Moyenne mobile exponentielle, initialisée sur la première valeur.
+// Les period - 1 premières valeurs servent de préchauffage.
+pub fn ema(values: &[f64], period: usize) -> Vec<f64> {
+    let alpha = 2. / (period.max(1) as f64 + 1.);
+    let mut result = Vec::with_capacity(values.len());
+    let mut previous: Option<f64> = None;
+    for value in values {
+        let current = match previous {
+            Some(previous) => previous + alpha * (value - previous),
+            None => *value,
+        };
+        result.push(current);
+        previous = Some(current);
+    }
+    result
+}
+
+pub fn sma(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let period = period.max(1);
+    let mut result = Vec::with_capacity(values.len());
+    let mut sum = 0.;
+    for (i, value) in values.iter().enumerate() {
+        sum += value;
+        if i >= period {
+            sum -= values[i - period];
+        }
+        result.push(if i + 1 >= period {
+            Some(sum / period as f64)
+        } else {
+            None
+        });
+    }
+    result
+}
+
+// Variation entre la valeur à index et la précédente
+pub fn slope(values: &[f64], index: usize) -> Option<f64> {
+    if index == 0 || index >= values.len() {
+        return None;
+    }
+    Some(values[index] - values[index - 1])
+}
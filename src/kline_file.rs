@@ -0,0 +1,306 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+
+use memmap2::Mmap;
+
+use crate::error::{Error, Result};
+use crate::patterns::MathKLine;
+use crate::series::*;
+
+// Fichier binaire de klines : un en-tête fixe puis un enregistrement de taille fixe par kline,
+// en little endian. Les champs texte de MathKLine y sont stockés en f64.
+const MAGIC: &[u8; 4] = b"SBKL";
+const VERSION: u32 = 1;
+const SYMBOL_SIZE: usize = 24;
+const INTERVAL_SIZE: usize = 8;
+pub const HEADER_SIZE: usize = 64;
+pub const RECORD_SIZE: usize = 11 * 8;
+
+// Position des champs dans un enregistrement
+const OPEN_TIME: usize = 0;
+const OPEN: usize = 8;
+const HIGH: usize = 16;
+const LOW: usize = 24;
+const CLOSE: usize = 32;
+const VOLUME: usize = 40;
+const CLOSE_TIME: usize = 48;
+const QUOTE_ASSET_VOLUME: usize = 56;
+const NUMBER_OF_TRADES: usize = 64;
+const TAKER_BUY_BASE_ASSET_VOLUME: usize = 72;
+const TAKER_BUY_QUOTE_ASSET_VOLUME: usize = 80;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KlineFileHeader {
+    pub symbol: String,
+    pub interval: String,
+    // open_time de la première kline, 0 si le fichier est vide
+    pub start: i64,
+    pub count: u64,
+}
+
+pub fn write_kline_file(
+    path: &str,
+    symbol: &str,
+    interval: &str,
+    klines: &[MathKLine],
+) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let header = KlineFileHeader {
+        symbol: symbol.to_string(),
+        interval: interval.to_string(),
+        start: klines.first().map_or(0, |kline| kline.open_time),
+        count: klines.len() as u64,
+    };
+    writer.write_all(&encode_header(&header)?)?;
+
+    let mut record = [0u8; RECORD_SIZE];
+    for kline in klines {
+        put_i64(&mut record, OPEN_TIME, kline.open_time);
+        put_f64(&mut record, OPEN, kline.open);
+        put_f64(&mut record, HIGH, kline.high);
+        put_f64(&mut record, LOW, kline.low);
+        put_f64(&mut record, CLOSE, kline.close);
+        put_f64(&mut record, VOLUME, parse_volume("volume", &kline.volume)?);
+        put_i64(&mut record, CLOSE_TIME, kline.close_time);
+        put_f64(
+            &mut record,
+            QUOTE_ASSET_VOLUME,
+            parse_volume("quote_asset_volume", &kline.quote_asset_volume)?,
+        );
+        put_i64(&mut record, NUMBER_OF_TRADES, kline.number_of_trades);
+        put_f64(
+            &mut record,
+            TAKER_BUY_BASE_ASSET_VOLUME,
+            parse_volume(
+                "taker_buy_base_asset_volume",
+                &kline.taker_buy_base_asset_volume,
+            )?,
+        );
+        put_f64(
+            &mut record,
+            TAKER_BUY_QUOTE_ASSET_VOLUME,
+            parse_volume(
+                "taker_buy_quote_asset_volume",
+                &kline.taker_buy_quote_asset_volume,
+            )?,
+        );
+        writer.write_all(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// Lecture à la demande depuis le fichier mappé en mémoire : seules les klines
+// accédées sont décodées, le reste reste sur disque
+pub struct KlineFile {
+    map: Mmap,
+    header: KlineFileHeader,
+}
+
+impl KlineFile {
+    pub fn open(path: &str) -> Result<Self> {
+        let file = File::open(path)?;
+        // Le fichier ne doit pas être modifié pendant la lecture
+        let map = unsafe { Mmap::map(&file)? };
+        let header = decode_header(&map)?;
+        // Un nombre de klines corrompu ne doit pas faire déborder le calcul de la taille
+        let expected = header
+            .count
+            .checked_mul(RECORD_SIZE as u64)
+            .and_then(|size| size.checked_add(HEADER_SIZE as u64));
+        if expected != Some(map.len() as u64) {
+            return Err(Error::InvalidData(format!(
+                "{} is {} bytes long, which does not match its {} klines",
+                path,
+                map.len(),
+                header.count
+            )));
+        }
+        Ok(KlineFile { map, header })
+    }
+
+    pub fn header(&self) -> &KlineFileHeader {
+        &self.header
+    }
+
+    pub fn len(&self) -> usize {
+        self.header.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.header.count == 0
+    }
+
+    pub fn open_time(&self, index: usize) -> i64 {
+        get_i64(self.record(index), OPEN_TIME)
+    }
+
+    pub fn close_time(&self, index: usize) -> i64 {
+        get_i64(self.record(index), CLOSE_TIME)
+    }
+
+    pub fn close(&self, index: usize) -> f64 {
+        get_f64(self.record(index), CLOSE)
+    }
+
+    pub fn kline(&self, index: usize) -> MathKLine {
+        let record = self.record(index);
+        MathKLine {
+            open_time: get_i64(record, OPEN_TIME),
+            open: get_f64(record, OPEN),
+            high: get_f64(record, HIGH),
+            low: get_f64(record, LOW),
+            close: get_f64(record, CLOSE),
+            volume: get_f64(record, VOLUME).to_string(),
+            close_time: get_i64(record, CLOSE_TIME),
+            quote_asset_volume: get_f64(record, QUOTE_ASSET_VOLUME).to_string(),
+            number_of_trades: get_i64(record, NUMBER_OF_TRADES),
+            taker_buy_base_asset_volume: get_f64(record, TAKER_BUY_BASE_ASSET_VOLUME).to_string(),
+            taker_buy_quote_asset_volume: get_f64(record, TAKER_BUY_QUOTE_ASSET_VOLUME).to_string(),
+        }
+    }
+
+    pub fn klines(&self, range: Range<usize>) -> Vec<MathKLine> {
+        range.map(|index| self.kline(index)).collect()
+    }
+
+    pub fn to_klines(&self) -> Vec<MathKLine> {
+        self.klines(0..self.len())
+    }
+
+    // Klines ouvertes dans [start, end[, trouvées par recherche dichotomique sur open_time
+    pub fn klines_between(&self, start: i64, end: i64) -> Vec<MathKLine> {
+        let first = self.partition_point(|index| self.open_time(index) < start);
+        let last = self.partition_point(|index| self.open_time(index) < end);
+        self.klines(first..last.max(first))
+    }
+
+    // Colonnes de prix seules, sans passer par MathKLine
+    pub fn to_series(&self, range: Range<usize>) -> PriceSeries {
+        let mut series = PriceSeries::with_capacity(range.len());
+        for index in range {
+            let record = self.record(index);
+            series.open_time.push(get_i64(record, OPEN_TIME));
+            series.open.push(get_f64(record, OPEN));
+            series.high.push(get_f64(record, HIGH));
+            series.low.push(get_f64(record, LOW));
+            series.close.push(get_f64(record, CLOSE));
+            series.volume.push(get_f64(record, VOLUME));
+            series.close_time.push(get_i64(record, CLOSE_TIME));
+        }
+        series
+    }
+
+    fn record(&self, index: usize) -> &[u8] {
+        assert!(
+            index < self.len(),
+            "kline {} out of bounds ({} klines)",
+            index,
+            self.len()
+        );
+        let start = HEADER_SIZE + index * RECORD_SIZE;
+        &self.map[start..start + RECORD_SIZE]
+    }
+
+    fn partition_point(&self, pred: impl Fn(usize) -> bool) -> usize {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let middle = (low + high) / 2;
+            if pred(middle) {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        low
+    }
+}
+
+impl KlineSource for KlineFile {
+    fn kline(&self, index: usize) -> MathKLine {
+        KlineFile::kline(self, index)
+    }
+}
+
+fn encode_header(header: &KlineFileHeader) -> Result<[u8; HEADER_SIZE]> {
+    let mut bytes = [0u8; HEADER_SIZE];
+    bytes[0..4].copy_from_slice(MAGIC);
+    bytes[4..8].copy_from_slice(&VERSION.to_le_bytes());
+    put_text(&mut bytes[8..8 + SYMBOL_SIZE], "symbol", &header.symbol)?;
+    put_text(
+        &mut bytes[32..32 + INTERVAL_SIZE],
+        "interval",
+        &header.interval,
+    )?;
+    put_i64(&mut bytes, 40, header.start);
+    bytes[48..56].copy_from_slice(&header.count.to_le_bytes());
+    Ok(bytes)
+}
+
+fn decode_header(bytes: &[u8]) -> Result<KlineFileHeader> {
+    if bytes.len() < HEADER_SIZE || &bytes[0..4] != MAGIC {
+        return Err(Error::InvalidData(String::from("not a binary kline file")));
+    }
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    if version != VERSION {
+        return Err(Error::InvalidData(format!(
+            "unsupported binary kline file version {}",
+            version
+        )));
+    }
+    Ok(KlineFileHeader {
+        symbol: get_text(&bytes[8..8 + SYMBOL_SIZE]),
+        interval: get_text(&bytes[32..32 + INTERVAL_SIZE]),
+        start: get_i64(bytes, 40),
+        count: u64::from_le_bytes(bytes[48..56].try_into().unwrap()),
+    })
+}
+
+fn parse_volume(field: &'static str, value: &str) -> Result<f64> {
+    // Les klines créées à la main n'ont pas toujours de volume
+    if value.is_empty() {
+        return Ok(0.);
+    }
+    value.parse().map_err(|_| Error::Parse {
+        field,
+        value: value.to_string(),
+    })
+}
+
+fn put_text(bytes: &mut [u8], field: &str, text: &str) -> Result<()> {
+    if text.len() > bytes.len() {
+        return Err(Error::InvalidParams(format!(
+            "{} {:?} is longer than {} bytes",
+            field,
+            text,
+            bytes.len()
+        )));
+    }
+    bytes[..text.len()].copy_from_slice(text.as_bytes());
+    Ok(())
+}
+
+fn get_text(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn put_i64(bytes: &mut [u8], offset: usize, value: i64) {
+    bytes[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put_f64(bytes: &mut [u8], offset: usize, value: f64) {
+    bytes[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn get_i64(bytes: &[u8], offset: usize) -> i64 {
+    i64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn get_f64(bytes: &[u8], offset: usize) -> f64 {
+    f64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
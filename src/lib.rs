@@ -1,5 +1,32 @@
+pub mod account;
 pub mod backtest;
+pub mod benchmark;
+pub mod config;
+pub mod confluence;
+pub mod engine;
+pub mod error;
+pub mod execution;
+pub mod filters;
+pub mod genetic;
+pub mod indicators;
+pub mod kline_file;
+pub mod timeframe;
 pub mod tools;
 pub mod patterns;
+pub mod lookahead;
+pub mod manifest;
+pub mod metrics;
+pub mod monte_carlo;
+pub mod paper;
+pub mod sampling;
+pub mod series;
+pub mod stability;
 pub mod strategies;
-pub mod strategies_creator;
\ No newline at end of file
+pub mod strategies_creator;
+pub mod storage;
+pub mod param_space;
+pub mod portfolio;
+pub mod progress;
+pub mod ranking;
+pub mod report;
+pub mod walk_forward;
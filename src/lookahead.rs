@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::*;
+use crate::error::Result;
+use crate::patterns::*;
+use crate::series::*;
+use crate::strategies::*;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum DivergenceKind {
+    // Signal présent avec l'historique complet mais absent une fois le futur retiré
+    Missing(Signal),
+    // Signal qui n'apparaît qu'avec l'historique tronqué
+    Extra(Signal),
+    Changed { full: Signal, truncated: Signal },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Divergence {
+    pub cut_index: usize,
+    pub cut_time: i64,
+    pub kind: DivergenceKind,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LookaheadReport {
+    pub name: StrategyName,
+    pub patterns_params: Vec<PatternConfig>,
+    pub total_signals: usize,
+    pub checked_cuts: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+impl LookaheadReport {
+    pub fn is_clean(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+// Rejoue la stratégie sur des historiques tronqués et vérifie que les signaux
+// émis jusqu'à la kline t sont identiques quand les klines après t sont retirées.
+// Les historiques sont coupés toutes les `step` klines ainsi que sur chaque kline de signal.
+// Seule la série est tronquée : les klines d'un signal sont les mêmes dans les deux cas.
+pub fn check_lookahead(
+    series: SeriesView,
+    klines: &dyn KlineSource,
+    strategy: &Strategy,
+    step: usize,
+) -> Result<LookaheadReport> {
+    let full: Vec<Signal> =
+        strategy.0(klines, series, None, strategy.1, strategy.2.clone(), false)?
+            .iter()
+            .map(Signal::from)
+            .collect();
+
+    let mut cuts: Vec<usize> = (0..series.len()).step_by(step.max(1)).collect();
+    for signal in full.iter() {
+        if let Ok(index) = series.close_time.binary_search(&signal.open_time) {
+            cuts.push(index);
+        }
+    }
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    let mut divergences = Vec::new();
+    for cut_index in cuts.iter().copied() {
+        let cut_time = series.close_time[cut_index];
+        let truncated: Vec<Signal> = strategy.0(
+            klines,
+            series.slice(0..cut_index + 1),
+            None,
+            strategy.1,
+            strategy.2.clone(),
+            false,
+        )?
+        .iter()
+        .map(Signal::from)
+        .collect();
+
+        let expected: Vec<&Signal> = full
+            .iter()
+            .filter(|signal| signal.open_time <= cut_time)
+            .collect();
+        for signal in expected.iter() {
+            match truncated
+                .iter()
+                .find(|other| other.open_time == signal.open_time)
+            {
+                None => divergences.push(Divergence {
+                    cut_index,
+                    cut_time,
+                    kind: DivergenceKind::Missing(**signal),
+                }),
+                Some(other) if other != *signal => divergences.push(Divergence {
+                    cut_index,
+                    cut_time,
+                    kind: DivergenceKind::Changed {
+                        full: **signal,
+                        truncated: *other,
+                    },
+                }),
+                _ => {}
+            }
+        }
+        for signal in truncated.iter() {
+            if !expected
+                .iter()
+                .any(|other| other.open_time == signal.open_time)
+            {
+                divergences.push(Divergence {
+                    cut_index,
+                    cut_time,
+                    kind: DivergenceKind::Extra(*signal),
+                });
+            }
+        }
+    }
+
+    Ok(LookaheadReport {
+        name: strategy.1.name,
+        patterns_params: strategy.2.iter().map(|params| params.to_config()).collect(),
+        total_signals: full.len(),
+        checked_cuts: cuts.len(),
+        divergences,
+    })
+}
@@ -0,0 +1,537 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use binance::api::Binance;
+use binance::general::General;
+use binance::market::Market;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use strategy_backtester::backtest::*;
+use strategy_backtester::config::*;
+use strategy_backtester::error::{Error, Result};
+use strategy_backtester::execution::*;
+use strategy_backtester::kline_file::*;
+use strategy_backtester::manifest::*;
+use strategy_backtester::metrics::*;
+use strategy_backtester::paper::*;
+use strategy_backtester::progress::*;
+use strategy_backtester::ranking::*;
+use strategy_backtester::report::*;
+use strategy_backtester::series::*;
+use strategy_backtester::storage::*;
+use strategy_backtester::strategies::*;
+use strategy_backtester::tools::*;
+
+#[derive(Parser)]
+#[command(
+    name = "strategy_backtester",
+    version,
+    about = "Backtest de stratégies sur des klines Binance"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(
+        about = "Télécharge des klines depuis Binance dans <folder><symbol>-<interval>.json"
+    )]
+    Download {
+        symbol: String,
+        interval: String,
+        #[arg(long, default_value_t = 10)]
+        iterations: usize,
+        #[arg(long, default_value_t = 1000)]
+        batch_size: u16,
+        #[arg(long, default_value = "data/")]
+        folder: String,
+    },
+    #[command(
+        about = "Convertit un export CSV de data.binance.vision au format JSON utilisé par run"
+    )]
+    Import {
+        input: String,
+        symbol: String,
+        interval: String,
+        #[arg(long, default_value = "data/")]
+        folder: String,
+    },
+    #[command(about = "Convert a JSON klines file to the memory-mapped binary format (.bin)")]
+    Convert {
+        input: String,
+        output: String,
+        symbol: String,
+        interval: String,
+    },
+    #[command(about = "Lance un backtest ou un balayage décrit dans un fichier TOML ou JSON")]
+    Run {
+        config: String,
+        #[arg(long)]
+        output: Option<String>,
+        #[arg(long)]
+        top: Option<usize>,
+    },
+    #[command(about = "Relance un backtest à partir du manifest.json écrit par run")]
+    Replay {
+        manifest: String,
+        #[arg(
+            long,
+            help = "Fichier de klines à utiliser à la place de celui du manifeste"
+        )]
+        data: Option<String>,
+        #[arg(long)]
+        output: Option<String>,
+        #[arg(long, help = "results.json d'origine, comparé aux résultats rejoués")]
+        compare: Option<String>,
+    },
+    #[command(about = "Paper trade the strategies of a run configuration on live Binance klines")]
+    Paper {
+        config: String,
+        #[arg(long, help = "Klines file to replay instead of the Binance websocket")]
+        replay: Option<String>,
+        #[arg(
+            long,
+            help = "Replay speed as a multiple of real time, no pause if absent"
+        )]
+        speed: Option<f64>,
+        #[arg(long, help = "Stop after this many closed klines")]
+        max_klines: Option<usize>,
+        #[arg(long)]
+        output: Option<String>,
+        #[command(flatten)]
+        execution: ExecutionArgs,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExecutionKind {
+    DryRun,
+    JsonLines,
+    // Clés lues dans BINANCE_API_KEY et BINANCE_SECRET_KEY
+    Binance,
+}
+
+#[derive(Args)]
+struct ExecutionArgs {
+    #[arg(
+        long,
+        help = "Send the orders of the paper traded strategies to this sink"
+    )]
+    execution: Option<ExecutionKind>,
+    #[arg(
+        long,
+        help = "File written by the json-lines sink",
+        default_value = "orders.jsonl"
+    )]
+    orders_file: String,
+    #[arg(
+        long,
+        help = "Index of the only strategy whose orders are sent, required by binance"
+    )]
+    execute_strategy: Option<usize>,
+    #[arg(long, help = "Capital used to size the orders, start_money if absent")]
+    capital: Option<f64>,
+}
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let cli = Cli::parse();
+    let outcome = match cli.command {
+        Command::Download {
+            symbol,
+            interval,
+            iterations,
+            batch_size,
+            folder,
+        } => download(symbol, interval, iterations, batch_size, folder),
+        Command::Import {
+            input,
+            symbol,
+            interval,
+            folder,
+        } => import(&input, symbol, interval, folder),
+        Command::Convert {
+            input,
+            output,
+            symbol,
+            interval,
+        } => convert(&input, &output, &symbol, &interval),
+        Command::Run {
+            config,
+            output,
+            top,
+        } => run(&config, output, top),
+        Command::Replay {
+            manifest,
+            data,
+            output,
+            compare,
+        } => replay(&manifest, data, output, compare),
+        Command::Paper {
+            config,
+            replay,
+            speed,
+            max_klines,
+            output,
+            execution,
+        } => paper(&config, replay, speed, max_klines, output, execution),
+    };
+    if let Err(error) = outcome {
+        log::error!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn download(
+    symbol: String,
+    interval: String,
+    iterations: usize,
+    batch_size: u16,
+    folder: String,
+) -> Result<()> {
+    let general: General = Binance::new(None, None);
+    let market: Market = Binance::new(None, None);
+    let server_time = general.get_server_time()?.server_time;
+    fs::create_dir_all(&folder)?;
+    let klines = retreive_test_data(
+        server_time,
+        &market,
+        symbol,
+        interval,
+        folder,
+        iterations,
+        batch_size,
+        true,
+    )?;
+    log::info!("{} klines téléchargées", klines.len());
+    Ok(())
+}
+
+fn import(input: &str, symbol: String, interval: String, folder: String) -> Result<()> {
+    let klines = import_binance_csv(input)?;
+    fs::create_dir_all(&folder)?;
+    write_data_to_file(&klines, symbol, interval, folder)?;
+    log::info!("{} klines importées", klines.len());
+    Ok(())
+}
+
+fn convert(input: &str, output: &str, symbol: &str, interval: &str) -> Result<()> {
+    let klines = load_klines_file(input)?;
+    write_kline_file(output, symbol, interval, &klines)?;
+    log::info!("{} klines written to {}", klines.len(), output);
+    Ok(())
+}
+
+fn run(path: &str, output: Option<String>, top: Option<usize>) -> Result<()> {
+    let mut config = RunConfig::from_file(path)?;
+    if output.is_some() {
+        config.output = output;
+    }
+    if let Some(top) = top {
+        config.top = top;
+    }
+
+    let (klines, series) = config.load_kline_data()?;
+    let mut strategies = config.create_strategies()?;
+    log::info!(
+        "{} klines chargées, {} stratégies à tester",
+        series.len(),
+        strategies.len()
+    );
+    let mut manifest = RunManifest::new(series.view(), &strategies);
+    manifest
+        .set_data_file(&config.data.file)?
+        .set_market(config.data.symbol.clone(), config.data.interval.clone())
+        .set_cost_model(config.costs)
+        .set_portfolio_limits(config.limits)
+        .set_benchmark(config.benchmark)
+        .set_filters(config.filters.clone())
+        .set_event_driven(config.event_driven)
+        .keep_ledger(config.keep_ledger);
+
+    let filters = config
+        .filters
+        .iter()
+        .map(|filter| filter.build(series.view()))
+        .collect::<Result<Vec<_>>>()?;
+    let (sender, receiver) = channel();
+    let progress = thread::spawn(move || log_progress(receiver));
+    let mut backtester = Backtester::with_series(klines, series, Some(sender), None, false);
+    for filter in filters {
+        backtester.add_signal_filter(filter);
+    }
+    backtester
+        .set_portfolio_limits(config.limits)
+        .set_cost_model(config.costs)
+        .keep_ledger(config.keep_ledger)
+        .add_strategies(&mut strategies);
+    if let Some(benchmark) = config.benchmark {
+        backtester.set_benchmark(benchmark);
+    }
+    if let Some(database) = &config.database {
+        if let Some(folder) = Path::new(database).parent() {
+            fs::create_dir_all(folder)?;
+        }
+        backtester.set_result_store(Arc::new(Mutex::new(ResultStore::open(database)?)));
+    }
+    if config.event_driven {
+        backtester.start_event_driven()?;
+    } else {
+        backtester.start()?;
+    }
+    let results = backtester.get_results();
+    // Ferme le canal pour que le thread de suivi se termine
+    drop(backtester);
+    let _ = progress.join();
+
+    print_ranking(&results, config.metric, config.top);
+
+    if let Some(output) = &config.output {
+        fs::create_dir_all(output)?;
+        fs::write(
+            format!("{}/results.json", output),
+            serde_json::to_string_pretty(&results)?,
+        )?;
+        manifest.write(&format!("{}/manifest.json", output))?;
+        HtmlReport::new(path)
+            .add_results(&results)
+            .set_metric(config.metric)
+            .set_detailed(config.top)
+            .write(&format!("{}/report.html", output))?;
+        log::info!("Résultats écrits dans {}", output);
+    }
+    Ok(())
+}
+
+fn print_ranking(results: &[StrategyResult], metric: Metric, top: usize) {
+    let ranked = Ranking::new().add_objective(metric, 1.).rank(results);
+    println!(
+        "{:>4} {:<14} {:>12} {:>10} {:>10} {:>8} {:>8}  paramètres",
+        "#", "stratégie", "capital", "rendement", "drawdown", "win %", "trades"
+    );
+    for (rank, entry) in ranked.iter().take(top).enumerate() {
+        let result = &entry.result;
+        let params: Vec<String> = result
+            .patterns_params
+            .iter()
+            .map(|params| params.to_string())
+            .collect();
+        println!(
+            "{:>4} {:<14} {:>12.2} {:>9.2}% {:>9.2}% {:>8.1} {:>8}  tp={} sl={} risk={} {}",
+            rank + 1,
+            result.name.to_string(),
+            result.final_money,
+            total_return(result) * 100.,
+            max_drawdown(result.start_money, &result.money_evolution) * 100.,
+            result.win_ratio,
+            result.total_closed,
+            result.strategy_params.tp_multiplier,
+            result.strategy_params.sl_multiplier,
+            result.strategy_params.risk_per_trade,
+            params.join(" ")
+        );
+    }
+}
+
+fn log_progress(receiver: Receiver<ProgressEvent>) {
+    let mut next_step = 10.;
+    for event in receiver {
+        match event {
+            ProgressEvent::Percent { percent, eta, .. } if percent >= next_step => {
+                match eta {
+                    Some(eta) => log::info!("{:.0}% done, {}s left", percent, eta.as_secs()),
+                    None => log::info!("{:.0}% done", percent),
+                }
+                next_step = (percent / 10.).floor() * 10. + 10.;
+            }
+            ProgressEvent::Finished {
+                completed,
+                cancelled: true,
+                ..
+            } => log::warn!("Run cancelled after {} strategies", completed),
+            _ => {}
+        }
+    }
+}
+
+fn replay(
+    path: &str,
+    data: Option<String>,
+    output: Option<String>,
+    compare: Option<String>,
+) -> Result<()> {
+    let manifest = RunManifest::read(path)?;
+    let data = data.or_else(|| manifest.data.file.clone()).ok_or_else(|| {
+        Error::InvalidParams(String::from(
+            "le manifeste ne référence aucun fichier de klines",
+        ))
+    })?;
+    if manifest.data.file.as_deref() == Some(data.as_str())
+        && manifest.data.file_hash != Some(file_hash(&data)?)
+    {
+        log::warn!(
+            "Attention : le fichier {} a changé depuis l'exécution",
+            data
+        );
+    }
+    let (klines, series) = load_kline_data(&data)?;
+    let results = manifest.replay(klines, series)?;
+    log::info!("{} stratégies rejouées", results.len());
+
+    let serialized = serde_json::to_string_pretty(&results)?;
+    if let Some(compare) = compare {
+        // Comparaison sur les valeurs JSON pour ne pas dépendre de la mise en forme
+        let original: serde_json::Value = serde_json::from_str(&fs::read_to_string(&compare)?)?;
+        let replayed: serde_json::Value = serde_json::from_str(&serialized)?;
+        if original != replayed {
+            return Err(Error::InvalidData(format!(
+                "les résultats diffèrent de {}",
+                compare
+            )));
+        }
+        log::info!("Résultats identiques à {}", compare);
+    }
+    if let Some(output) = output {
+        fs::create_dir_all(&output)?;
+        fs::write(format!("{}/results.json", output), serialized)?;
+        log::info!("Résultats écrits dans {}", output);
+    }
+    Ok(())
+}
+
+fn paper(
+    path: &str,
+    replay: Option<String>,
+    speed: Option<f64>,
+    max_klines: Option<usize>,
+    output: Option<String>,
+    execution: ExecutionArgs,
+) -> Result<()> {
+    let config = RunConfig::from_file(path)?;
+    let (strategies, skipped): (Vec<_>, Vec<_>) = config
+        .create_strategies()?
+        .into_iter()
+        .partition(|strategy| trade_finder(strategy.1.name).is_some());
+    if !skipped.is_empty() {
+        log::warn!(
+            "{} strategies cannot run kline by kline and are not paper traded",
+            skipped.len()
+        );
+    }
+    let symbol = config.data.symbol.clone().unwrap_or_default();
+    let interval = config.data.interval.clone().unwrap_or_default();
+    let replay = replay.map(|file| load_klines_file(&file)).transpose()?;
+    // Les filtres sont calculés sur toutes les klines à l'avance, ce qui n'est possible
+    // que pour un fichier rejoué
+    let filters = match &replay {
+        Some(klines) => {
+            let series = PriceSeries::from_klines(klines);
+            config
+                .filters
+                .iter()
+                .map(|filter| filter.build(series.view()))
+                .collect::<Result<Vec<_>>>()?
+        }
+        None if !config.filters.is_empty() => {
+            return Err(Error::Config(String::from(
+                "signal filters can only be applied when replaying a klines file",
+            )))
+        }
+        None => Vec::new(),
+    };
+
+    log::info!("Paper trading {} strategies", strategies.len());
+    let mut trader = PaperTrader::new(strategies, config.limits)?;
+    trader.set_cost_model(config.costs);
+    for filter in filters {
+        trader.add_signal_filter(filter);
+    }
+    let mut stream: Box<dyn KlineStream> = match replay {
+        Some(klines) => {
+            let mut stream = FileReplayStream::new(klines, &symbol, &interval);
+            if let Some(speed) = speed {
+                stream.set_speed(speed);
+            }
+            Box::new(stream)
+        }
+        None if symbol.is_empty() || interval.is_empty() => {
+            return Err(Error::Config(String::from(
+                "data.symbol and data.interval are needed to paper trade live klines",
+            )))
+        }
+        None => {
+            let mut stream = WebsocketKlineStream::connect(&symbol, &interval);
+            stream.set_cancellation_token(trader.cancellation_token());
+            Box::new(stream)
+        }
+    };
+    if let Some(kind) = execution.execution {
+        let sink: Box<dyn ExecutionSink> = match kind {
+            ExecutionKind::DryRun => Box::new(DryRunSink::new()),
+            ExecutionKind::JsonLines => Box::new(JsonLinesSink::create(&execution.orders_file)?),
+            ExecutionKind::Binance if execution.execute_strategy.is_none() => {
+                return Err(Error::Config(String::from(
+                    "--execute-strategy is required to send orders to Binance",
+                )))
+            }
+            ExecutionKind::Binance => Box::new(BinanceSink::new(
+                env_var("BINANCE_API_KEY")?,
+                env_var("BINANCE_SECRET_KEY")?,
+                &symbol,
+            )?),
+        };
+        let mut router = ExecutionRouter::new(
+            sink,
+            &symbol,
+            execution.capital.unwrap_or(config.start_money),
+        );
+        if let Some(strategy) = execution.execute_strategy {
+            router.set_strategy(strategy);
+        }
+        trader.set_execution(router);
+    }
+    let klines = trader.run(stream.as_mut(), max_klines, &mut |order| {
+        log::info!(
+            "{} #{} at {}: {:?}",
+            order.strategy_params.name,
+            order.strategy,
+            order.time,
+            order.order
+        );
+        Ok(())
+    })?;
+    log::info!("{} klines processed", klines);
+    if let Some(router) = trader.execution() {
+        let mut states: BTreeMap<String, usize> = BTreeMap::new();
+        for order in router.orders() {
+            *states.entry(format!("{:?}", order.state())).or_default() += 1;
+        }
+        for (state, count) in states {
+            log::info!("{} orders {}", count, state);
+        }
+    }
+
+    let results = trader.results();
+    print_ranking(&results, config.metric, config.top);
+    if let Some(output) = output.or(config.output) {
+        fs::create_dir_all(&output)?;
+        fs::write(
+            format!("{}/paper_results.json", output),
+            serde_json::to_string_pretty(&results)?,
+        )?;
+        log::info!("Résultats écrits dans {}", output);
+    }
+    Ok(())
+}
+
+fn env_var(name: &str) -> Result<String> {
+    std::env::var(name).map_err(|_| Error::Config(format!("{} is not set", name)))
+}
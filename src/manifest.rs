@@ -0,0 +1,231 @@
+use std::fs;
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::account::*;
+use crate::backtest::*;
+use crate::benchmark::*;
+use crate::error::{Error, Result};
+use crate::filters::*;
+use crate::patterns::*;
+use crate::series::*;
+use crate::storage::*;
+use crate::strategies::*;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DataManifest {
+    #[serde(default)]
+    pub file: Option<String>,
+    // Hash FNV du contenu brut du fichier
+    #[serde(default)]
+    pub file_hash: Option<String>,
+    // Hash des klines chargées, indépendant du format du fichier
+    pub dataset_hash: String,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub interval: Option<String>,
+    pub klines: usize,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StrategyManifest {
+    pub params: StrategyParams,
+    pub patterns_params: Vec<PatternConfig>,
+}
+
+impl StrategyManifest {
+    pub fn from_strategy(strategy: &Strategy) -> Self {
+        StrategyManifest {
+            params: strategy.1,
+            patterns_params: strategy.2.iter().map(|params| params.to_config()).collect(),
+        }
+    }
+
+    pub fn to_strategy(&self) -> Result<Strategy> {
+        let patterns_params: Vec<Arc<dyn PatternParams>> = self
+            .patterns_params
+            .iter()
+            .map(PatternConfig::to_params)
+            .collect();
+        validate_strategy(&self.params, &patterns_params)?;
+        let func = strategy_func_for(self.params.name).ok_or_else(|| {
+            Error::InvalidParams(format!("unknown strategy {}", self.params.name))
+        })?;
+        Ok((func, self.params, Arc::new(patterns_params)))
+    }
+}
+
+pub fn file_hash(path: &str) -> Result<String> {
+    let mut hasher = Fnv::default();
+    hasher.write(&fs::read(path)?);
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+// Tout ce qu'il faut pour relancer un backtest et retrouver exactement les mêmes résultats
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub crate_version: String,
+    pub created_at: i64,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    pub data: DataManifest,
+    pub costs: CostModel,
+    pub limits: PortfolioLimits,
+    #[serde(default)]
+    pub benchmark: Option<BenchmarkConfig>,
+    #[serde(default)]
+    pub filters: Vec<FilterConfig>,
+    #[serde(default)]
+    pub event_driven: bool,
+    #[serde(default)]
+    pub keep_ledger: bool,
+    #[serde(default)]
+    pub only_potential: bool,
+    pub strategies: Vec<StrategyManifest>,
+}
+
+impl RunManifest {
+    pub fn new(series: SeriesView, strategies: &[Strategy]) -> Self {
+        RunManifest {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now().timestamp_millis(),
+            seed: None,
+            data: DataManifest {
+                file: None,
+                file_hash: None,
+                dataset_hash: dataset_hash(series),
+                symbol: None,
+                interval: None,
+                klines: series.len(),
+                start_time: series.open_time.first().copied().unwrap_or(0),
+                end_time: series.close_time.last().copied().unwrap_or(0),
+            },
+            costs: CostModel::default(),
+            limits: PortfolioLimits::unlimited(),
+            benchmark: None,
+            filters: Vec::new(),
+            event_driven: false,
+            keep_ledger: false,
+            only_potential: false,
+            strategies: strategies
+                .iter()
+                .map(StrategyManifest::from_strategy)
+                .collect(),
+        }
+    }
+
+    pub fn set_data_file(&mut self, path: &str) -> Result<&mut Self> {
+        self.data.file_hash = Some(file_hash(path)?);
+        self.data.file = Some(path.to_string());
+        Ok(self)
+    }
+
+    pub fn set_market(&mut self, symbol: Option<String>, interval: Option<String>) -> &mut Self {
+        self.data.symbol = symbol;
+        self.data.interval = interval;
+        self
+    }
+
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn set_cost_model(&mut self, costs: CostModel) -> &mut Self {
+        self.costs = costs;
+        self
+    }
+
+    pub fn set_portfolio_limits(&mut self, limits: PortfolioLimits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn set_benchmark(&mut self, benchmark: Option<BenchmarkConfig>) -> &mut Self {
+        self.benchmark = benchmark;
+        self
+    }
+
+    pub fn set_filters(&mut self, filters: Vec<FilterConfig>) -> &mut Self {
+        self.filters = filters;
+        self
+    }
+
+    pub fn set_event_driven(&mut self, event_driven: bool) -> &mut Self {
+        self.event_driven = event_driven;
+        self
+    }
+
+    pub fn keep_ledger(&mut self, keep_ledger: bool) -> &mut Self {
+        self.keep_ledger = keep_ledger;
+        self
+    }
+
+    pub fn set_only_potential(&mut self, only_potential: bool) -> &mut Self {
+        self.only_potential = only_potential;
+        self
+    }
+
+    pub fn write(&self, path: &str) -> Result<()> {
+        Ok(fs::write(path, serde_json::to_string_pretty(self)?)?)
+    }
+
+    pub fn read(path: &str) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn strategies(&self) -> Result<Vec<Strategy>> {
+        self.strategies
+            .iter()
+            .map(StrategyManifest::to_strategy)
+            .collect()
+    }
+
+    pub fn matches_data(&self, series: SeriesView) -> bool {
+        dataset_hash(series) == self.data.dataset_hash
+    }
+
+    // Rejoue le backtest décrit par le manifeste, sur les mêmes klines uniquement
+    pub fn replay(
+        &self,
+        klines: Arc<dyn KlineSource>,
+        series: PriceSeries,
+    ) -> Result<Vec<StrategyResult>> {
+        if !self.matches_data(series.view()) {
+            return Err(Error::InvalidData(format!(
+                "klines do not match the manifest data set {}",
+                self.data.dataset_hash
+            )));
+        }
+        let mut strategies = self.strategies()?;
+        let filters = self
+            .filters
+            .iter()
+            .map(|filter| filter.build(series.view()))
+            .collect::<Result<Vec<_>>>()?;
+        let mut backtester =
+            Backtester::with_series(klines, series, None, None, self.only_potential);
+        for filter in filters {
+            backtester.add_signal_filter(filter);
+        }
+        backtester
+            .set_portfolio_limits(self.limits)
+            .set_cost_model(self.costs)
+            .keep_ledger(self.keep_ledger)
+            .add_strategies(&mut strategies);
+        if let Some(benchmark) = self.benchmark {
+            backtester.set_benchmark(benchmark);
+        }
+        if self.event_driven {
+            backtester.start_event_driven()?;
+        } else {
+            backtester.start()?;
+        }
+        Ok(backtester.get_results())
+    }
+}
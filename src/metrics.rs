@@ -0,0 +1,108 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::*;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Metric {
+    FinalMoney,
+    Return,
+    WinRatio,
+    Efficiency,
+    TotalClosed,
+    MaxDrawdown,
+    ProfitPerTrade,
+    // Rendement au-delà du buy-and-hold, NaN sans benchmark
+    ExcessReturn,
+    Alpha,
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Metric::FinalMoney => write!(f, "final_money"),
+            Metric::Return => write!(f, "return"),
+            Metric::WinRatio => write!(f, "win_ratio"),
+            Metric::Efficiency => write!(f, "efficiency"),
+            Metric::TotalClosed => write!(f, "total_closed"),
+            Metric::MaxDrawdown => write!(f, "max_drawdown"),
+            Metric::ProfitPerTrade => write!(f, "profit_per_trade"),
+            Metric::ExcessReturn => write!(f, "excess_return"),
+            Metric::Alpha => write!(f, "alpha"),
+        }
+    }
+}
+
+impl Metric {
+    pub fn value(&self, result: &StrategyResult) -> f64 {
+        match self {
+            Metric::FinalMoney => result.final_money,
+            Metric::Return => total_return(result),
+            Metric::WinRatio => result.win_ratio as f64,
+            Metric::Efficiency => result.efficiency as f64,
+            Metric::TotalClosed => result.total_closed as f64,
+            Metric::MaxDrawdown => max_drawdown(result.start_money, &result.money_evolution),
+            Metric::ProfitPerTrade => {
+                if result.total_closed == 0 {
+                    0.
+                } else {
+                    (result.final_money - result.start_money) / result.total_closed as f64
+                }
+            }
+            Metric::ExcessReturn => result
+                .benchmark
+                .map_or(f64::NAN, |benchmark| benchmark.excess_return),
+            Metric::Alpha => result
+                .benchmark
+                .map_or(f64::NAN, |benchmark| benchmark.alpha),
+        }
+    }
+
+    pub fn higher_is_better(&self) -> bool {
+        !matches!(self, Metric::MaxDrawdown)
+    }
+
+    // Valeur orientée pour que le meilleur résultat ait toujours le score le plus haut
+    pub fn score(&self, result: &StrategyResult) -> f64 {
+        let value = self.value(result);
+        let value = if value.is_nan() {
+            f64::NEG_INFINITY
+        } else {
+            value
+        };
+        if self.higher_is_better() {
+            value
+        } else {
+            -value
+        }
+    }
+}
+
+pub fn total_return(result: &StrategyResult) -> f64 {
+    if result.start_money == 0. {
+        return 0.;
+    }
+    result.final_money / result.start_money - 1.
+}
+
+// Plus forte baisse depuis un sommet, en fraction du sommet
+pub fn max_drawdown(start_money: f64, money_evolution: &[f64]) -> f64 {
+    let mut peak = start_money;
+    let mut drawdown: f64 = 0.;
+    for money in money_evolution {
+        peak = peak.max(*money);
+        if peak > 0. {
+            drawdown = drawdown.max((peak - money) / peak);
+        }
+    }
+    drawdown
+}
+
+pub fn best_result_index(results: &[StrategyResult], metric: Metric) -> Option<usize> {
+    results
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| metric.score(a).total_cmp(&metric.score(b)))
+        .map(|(i, _)| i)
+}
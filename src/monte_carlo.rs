@@ -0,0 +1,193 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::*;
+use crate::error::{Error, Result};
+use crate::metrics::*;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Resampling {
+    // Mélange l'ordre des trades
+    Reshuffle,
+    // Tire autant de trades que le ledger, avec remise
+    Bootstrap,
+    // Retire chaque trade avec la probabilité donnée
+    Skip(f64),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonteCarloConfig {
+    pub iterations: usize,
+    pub resampling: Resampling,
+    pub seed: u64,
+    // Ruine si le capital descend sous cette fraction du capital de départ
+    pub ruin_threshold: f64,
+    // Niveau des intervalles de confiance (0.95 = percentiles 2.5 et 97.5)
+    pub confidence: f64,
+    pub percentiles: Vec<f64>,
+}
+
+impl Default for MonteCarloConfig {
+    fn default() -> Self {
+        MonteCarloConfig {
+            iterations: 1000,
+            resampling: Resampling::Reshuffle,
+            seed: 0,
+            ruin_threshold: 0.5,
+            confidence: 0.95,
+            percentiles: vec![5., 25., 50., 75., 95.],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PercentileCurve {
+    pub percentile: f64,
+    pub equity: Vec<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ConfidenceInterval {
+    pub level: f64,
+    pub lower: f64,
+    pub median: f64,
+    pub upper: f64,
+    pub mean: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonteCarloReport {
+    pub iterations: usize,
+    pub resampling: Resampling,
+    pub start_money: f64,
+    pub percentile_curves: Vec<PercentileCurve>,
+    pub probability_of_ruin: f64,
+    pub final_money: ConfidenceInterval,
+    pub max_drawdown: ConfidenceInterval,
+}
+
+// Percentile (0-100) par interpolation linéaire sur des valeurs triées
+pub fn percentile(sorted: &[f64], percentile: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.;
+    }
+    let rank = (percentile / 100.).clamp(0., 1.) * (sorted.len() - 1) as f64;
+    let low = rank.floor() as usize;
+    let high = rank.ceil() as usize;
+    sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64)
+}
+
+fn confidence_interval(mut values: Vec<f64>, level: f64) -> ConfidenceInterval {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let tail = (1. - level) / 2. * 100.;
+    let mean = if values.is_empty() {
+        0.
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    };
+    ConfidenceInterval {
+        level,
+        lower: percentile(&values, tail),
+        median: percentile(&values, 50.),
+        upper: percentile(&values, 100. - tail),
+        mean,
+    }
+}
+
+fn resample(returns: &[f64], resampling: Resampling, rng: &mut StdRng) -> Vec<f64> {
+    match resampling {
+        Resampling::Reshuffle => {
+            let mut path = returns.to_vec();
+            path.shuffle(rng);
+            path
+        }
+        Resampling::Bootstrap => (0..returns.len())
+            .map(|_| returns[rng.gen_range(0..returns.len())])
+            .collect(),
+        Resampling::Skip(probability) => returns
+            .iter()
+            .copied()
+            .filter(|_| !rng.gen_bool(probability))
+            .collect(),
+    }
+}
+
+pub fn run_monte_carlo(
+    start_money: f64,
+    ledger: &[TradeRecord],
+    config: &MonteCarloConfig,
+) -> Result<MonteCarloReport> {
+    if let Resampling::Skip(probability) = config.resampling {
+        if !(0. ..=1.).contains(&probability) {
+            return Err(Error::InvalidParams(format!(
+                "skip probability must be between 0 and 1, got {}",
+                probability
+            )));
+        }
+    }
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let returns: Vec<f64> = ledger.iter().map(|record| record.return_ratio()).collect();
+    let ruin_money = start_money * config.ruin_threshold;
+
+    let mut paths: Vec<Vec<f64>> = Vec::with_capacity(config.iterations);
+    let mut final_moneys = Vec::with_capacity(config.iterations);
+    let mut max_drawdowns = Vec::with_capacity(config.iterations);
+    let mut ruined = 0;
+    for _ in 0..config.iterations {
+        let mut money = start_money;
+        let mut path = Vec::with_capacity(returns.len());
+        if !returns.is_empty() {
+            for ratio in resample(&returns, config.resampling, &mut rng) {
+                money *= 1. + ratio;
+                path.push(money);
+            }
+        }
+        if path.iter().any(|money| *money <= ruin_money) {
+            ruined += 1;
+        }
+        final_moneys.push(money);
+        max_drawdowns.push(max_drawdown(start_money, &path));
+        // Les chemins plus courts (trades retirés) gardent leur dernier capital
+        path.resize(returns.len(), money);
+        paths.push(path);
+    }
+
+    let percentile_curves = config
+        .percentiles
+        .iter()
+        .map(|p| PercentileCurve {
+            percentile: *p,
+            equity: (0..returns.len())
+                .map(|i| {
+                    let mut values: Vec<f64> = paths.iter().map(|path| path[i]).collect();
+                    values.sort_by(|a, b| a.total_cmp(b));
+                    percentile(&values, *p)
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(MonteCarloReport {
+        iterations: config.iterations,
+        resampling: config.resampling,
+        start_money,
+        percentile_curves,
+        probability_of_ruin: if config.iterations == 0 {
+            0.
+        } else {
+            ruined as f64 / config.iterations as f64
+        },
+        final_money: confidence_interval(final_moneys, config.confidence),
+        max_drawdown: confidence_interval(max_drawdowns, config.confidence),
+    })
+}
+
+pub fn run_monte_carlo_on_result(
+    result: &StrategyResult,
+    config: &MonteCarloConfig,
+) -> Option<Result<MonteCarloReport>> {
+    let ledger = result.ledger.as_ref()?;
+    Some(run_monte_carlo(result.start_money, ledger, config))
+}
@@ -0,0 +1,325 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use binance::model::Kline;
+use binance::websockets::{WebSockets, WebsocketEvent};
+
+use crate::account::*;
+use crate::backtest::*;
+use crate::config::*;
+use crate::engine::*;
+use crate::error::{Error, Result};
+use crate::execution::*;
+use crate::filters::*;
+use crate::patterns::*;
+use crate::progress::*;
+use crate::strategies::*;
+
+// Source de klines en temps réel. Ok(None) quand le flux est terminé.
+pub trait KlineStream {
+    fn next_kline(&mut self) -> Result<Option<Kline>>;
+}
+
+// Rejoue un fichier de klines, en attendant entre deux klines leur écart de clôture
+// divisé par speed. Sans speed, les klines sont envoyées sans attente.
+pub struct FileReplayStream {
+    klines: Vec<MathKLine>,
+    symbol: String,
+    interval: String,
+    position: usize,
+    speed: Option<f64>,
+}
+
+impl FileReplayStream {
+    pub fn new(klines: Vec<MathKLine>, symbol: &str, interval: &str) -> Self {
+        FileReplayStream {
+            klines,
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            position: 0,
+            speed: None,
+        }
+    }
+
+    pub fn open(path: &str, symbol: &str, interval: &str) -> Result<Self> {
+        Ok(FileReplayStream::new(
+            load_klines_file(path)?,
+            symbol,
+            interval,
+        ))
+    }
+
+    pub fn set_speed(&mut self, speed: f64) -> &mut Self {
+        self.speed = Some(speed);
+        self
+    }
+}
+
+impl KlineStream for FileReplayStream {
+    fn next_kline(&mut self) -> Result<Option<Kline>> {
+        let Some(kline) = self.klines.get(self.position) else {
+            return Ok(None);
+        };
+        if let (Some(speed), Some(previous)) = (self.speed, self.position.checked_sub(1)) {
+            let elapsed = (kline.close_time - self.klines[previous].close_time).max(0);
+            if speed > 0. {
+                thread::sleep(Duration::from_millis((elapsed as f64 / speed) as u64));
+            }
+        }
+        self.position += 1;
+        Ok(Some(math_kline_to_kline(
+            kline,
+            &self.symbol,
+            &self.interval,
+        )))
+    }
+}
+
+// Délai entre deux vérifications de l'annulation en attendant une kline
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(250);
+
+// Klines clôturées du flux websocket <symbol>@kline_<interval> de Binance,
+// reçues par un thread dédié
+pub struct WebsocketKlineStream {
+    // Les erreurs du thread sont transmises sous forme de texte
+    receiver: Receiver<std::result::Result<Kline, String>>,
+    running: Arc<AtomicBool>,
+    cancellation: CancellationToken,
+}
+
+impl WebsocketKlineStream {
+    pub fn connect(symbol: &str, interval: &str) -> Self {
+        let subscription = format!("{}@kline_{}", symbol.to_lowercase(), interval);
+        let running = Arc::new(AtomicBool::new(true));
+        let (sender, receiver) = channel();
+        let thread_running = running.clone();
+        thread::spawn(move || {
+            let klines = sender.clone();
+            // La fermeture doit renvoyer le Result de binance, dont l'erreur est volumineuse
+            #[allow(clippy::result_large_err)]
+            let mut socket = WebSockets::new(move |event| {
+                if let WebsocketEvent::Kline(event) = event {
+                    // Les mises à jour de la kline en cours sont ignorées
+                    if event.kline.is_final_bar {
+                        let _ = klines.send(Ok(event.kline));
+                    }
+                }
+                Ok(())
+            });
+            let outcome = match socket.connect(&subscription) {
+                Ok(_) => socket.event_loop(&thread_running),
+                Err(error) => Err(error),
+            };
+            if let Err(error) = outcome {
+                let _ = sender.send(Err(error.to_string()));
+            }
+            let _ = socket.disconnect();
+        });
+        WebsocketKlineStream {
+            receiver,
+            running,
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    // Le flux se termine dès l'annulation, sans attendre la prochaine kline
+    pub fn set_cancellation_token(&mut self, cancellation: CancellationToken) -> &mut Self {
+        self.cancellation = cancellation;
+        self
+    }
+}
+
+impl KlineStream for WebsocketKlineStream {
+    fn next_kline(&mut self) -> Result<Option<Kline>> {
+        while !self.cancellation.is_cancelled() {
+            match self.receiver.recv_timeout(RECEIVE_TIMEOUT) {
+                Ok(kline) => return kline.map(Some).map_err(Error::Binance),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Drop for WebsocketKlineStream {
+    fn drop(&mut self) {
+        // Le thread s'arrête au prochain message reçu
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+pub fn math_kline_to_kline(kline: &MathKLine, symbol: &str, interval: &str) -> Kline {
+    Kline {
+        open_time: kline.open_time,
+        close_time: kline.close_time,
+        symbol: symbol.to_string(),
+        interval: interval.to_string(),
+        first_trade_id: 0,
+        last_trade_id: 0,
+        open: kline.open.to_string(),
+        close: kline.close.to_string(),
+        high: kline.high.to_string(),
+        low: kline.low.to_string(),
+        volume: kline.volume.clone(),
+        number_of_trades: kline.number_of_trades,
+        is_final_bar: true,
+        quote_asset_volume: kline.quote_asset_volume.clone(),
+        taker_buy_base_asset_volume: kline.taker_buy_base_asset_volume.clone(),
+        taker_buy_quote_asset_volume: kline.taker_buy_quote_asset_volume.clone(),
+        ignore_me: String::new(),
+    }
+}
+
+// Ordre accepté par une stratégie, à la clôture de la kline time
+#[derive(Clone, Copy, Debug)]
+pub struct PaperOrder {
+    pub strategy: usize,
+    pub strategy_params: StrategyParams,
+    pub time: i64,
+    pub order: Order,
+}
+
+// Exécute les stratégies sur des klines reçues une à une, chacune avec son compte fictif,
+// comme Backtester::start_event_driven
+pub struct PaperTrader {
+    engines: Vec<EventEngine>,
+    strategies: Vec<StrategyParams>,
+    cancellation: CancellationToken,
+    // Reproduit les trades des comptes fictifs sur un sink d'exécution
+    execution: Option<ExecutionRouter>,
+    last_open_time: Option<i64>,
+    klines: usize,
+}
+
+impl PaperTrader {
+    pub fn new(strategies: Vec<Strategy>, limits: PortfolioLimits) -> Result<Self> {
+        let mut engines = Vec::with_capacity(strategies.len());
+        let mut strategies_params = Vec::with_capacity(strategies.len());
+        for strategy in strategies {
+            validate_strategy(&strategy.1, &strategy.2)?;
+            if trade_finder(strategy.1.name).is_none() {
+                return Err(Error::InvalidParams(format!(
+                    "strategy {} cannot be paper traded",
+                    strategy.1.name
+                )));
+            }
+            strategies_params.push(strategy.1);
+            engines.push(EventEngine::new(
+                Box::new(PatternBarStrategy::new(strategy)),
+                limits,
+            ));
+        }
+        Ok(PaperTrader {
+            engines,
+            strategies: strategies_params,
+            cancellation: CancellationToken::new(),
+            execution: None,
+            last_open_time: None,
+            klines: 0,
+        })
+    }
+
+    pub fn set_cost_model(&mut self, costs: CostModel) -> &mut Self {
+        for engine in self.engines.iter_mut() {
+            engine.set_cost_model(costs);
+        }
+        self
+    }
+
+    pub fn set_cancellation_token(&mut self, cancellation: CancellationToken) -> &mut Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    // Comme Backtester::add_signal_filter, pour chaque stratégie
+    pub fn add_signal_filter(&mut self, filter: Arc<dyn SignalFilter>) -> &mut Self {
+        for engine in self.engines.iter_mut() {
+            engine.add_signal_filter(filter.clone());
+        }
+        self
+    }
+
+    pub fn set_execution(&mut self, execution: ExecutionRouter) -> &mut Self {
+        self.execution = Some(execution);
+        self
+    }
+
+    pub fn execution(&self) -> Option<&ExecutionRouter> {
+        self.execution.as_ref()
+    }
+
+    // Nombre de klines clôturées traitées
+    pub fn klines(&self) -> usize {
+        self.klines
+    }
+
+    // Les klines non clôturées et celles déjà reçues (reconnexion du flux) sont ignorées
+    pub fn on_kline(&mut self, kline: &Kline) -> Result<Vec<PaperOrder>> {
+        if !kline.is_final_bar
+            || self
+                .last_open_time
+                .is_some_and(|last| kline.open_time <= last)
+        {
+            return Ok(Vec::new());
+        }
+        let math_kline = Backtester::kline_to_math_kline(kline)?;
+        self.last_open_time = Some(kline.open_time);
+        self.klines += 1;
+        let mut orders = Vec::new();
+        for (i, engine) in self.engines.iter_mut().enumerate() {
+            for order in engine.on_kline(math_kline.clone()) {
+                orders.push(PaperOrder {
+                    strategy: i,
+                    strategy_params: self.strategies[i],
+                    time: kline.close_time,
+                    order,
+                });
+            }
+        }
+        // Le sink suit les trades acceptés, ouverts, écartés ou fermés par chaque compte fictif
+        if let Some(execution) = &mut self.execution {
+            for (i, engine) in self.engines.iter().enumerate() {
+                execution.on_trades(i, self.strategies[i], engine.trades(), kline.close_time)?;
+            }
+        }
+        Ok(orders)
+    }
+
+    // Traite le flux jusqu'à sa fin, l'annulation ou max_klines klines clôturées.
+    // on_order reçoit chaque ordre accepté.
+    pub fn run(
+        &mut self,
+        stream: &mut dyn KlineStream,
+        max_klines: Option<usize>,
+        on_order: &mut dyn FnMut(&PaperOrder) -> Result<()>,
+    ) -> Result<usize> {
+        let start = self.klines;
+        while !self.cancellation.is_cancelled()
+            && max_klines.is_none_or(|max| self.klines - start < max)
+        {
+            let Some(kline) = stream.next_kline()? else {
+                break;
+            };
+            for order in self.on_kline(&kline)? {
+                on_order(&order)?;
+            }
+        }
+        Ok(self.klines - start)
+    }
+
+    pub fn trades(&self, strategy: usize) -> &Vec<Trade> {
+        self.engines[strategy].trades()
+    }
+
+    pub fn results(&self) -> Vec<StrategyResult> {
+        self.engines.iter().map(EventEngine::result).collect()
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::BTreeMap;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::*;
+use crate::confluence::*;
+use crate::patterns::*;
+use crate::sampling::*;
+use crate::strategies::*;
+use crate::strategies_creator::*;
+
+// Tolérance pour inclure la borne max malgré les erreurs d'arrondi de (max - min) / step
+const STEP_EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ParamValue {
+    Float(f64),
+    Integer(i64),
+    Categorical(String),
+    Boolean(bool),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum DimensionKind {
+    Float { min: f64, max: f64, step: f64 },
+    Integer { min: i64, max: i64, step: i64 },
+    Categorical(Vec<String>),
+    Boolean,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dimension {
+    pub name: String,
+    pub kind: DimensionKind,
+}
+
+impl Dimension {
+    // Nombre de valeurs de la grille
+    pub fn len(&self) -> usize {
+        match &self.kind {
+            DimensionKind::Float { min, max, step } => {
+                if max < min {
+                    0
+                } else if *step <= 0. || max == min {
+                    1
+                } else {
+                    ((max - min) / step + STEP_EPSILON).floor() as usize + 1
+                }
+            }
+            DimensionKind::Integer { min, max, step } => {
+                if max < min {
+                    0
+                } else if *step <= 0 || max == min {
+                    1
+                } else {
+                    ((max - min) / step) as usize + 1
+                }
+            }
+            DimensionKind::Categorical(values) => values.len(),
+            DimensionKind::Boolean => 2,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Valeur d'indice i, calculée depuis min pour éviter d'accumuler les erreurs du pas
+    pub fn value(&self, i: usize) -> ParamValue {
+        match &self.kind {
+            DimensionKind::Float { min, step, .. } => ParamValue::Float(min + i as f64 * step),
+            DimensionKind::Integer { min, step, .. } => ParamValue::Integer(min + i as i64 * step),
+            DimensionKind::Categorical(values) => ParamValue::Categorical(values[i].clone()),
+            DimensionKind::Boolean => ParamValue::Boolean(i == 1),
+        }
+    }
+
+    pub fn values(&self) -> Vec<ParamValue> {
+        (0..self.len()).map(|i| self.value(i)).collect()
+    }
+
+    // Passe de [0, 1) à une valeur de la dimension. Les flottants ne sont pas ramenés sur la grille.
+    // None si la dimension est vide.
+    pub fn from_unit(&self, unit: f64, method: SamplingMethod) -> Option<ParamValue> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(match &self.kind {
+            DimensionKind::Float { min, max, .. } => {
+                ParamValue::Float(scale_unit(unit, *min, *max, method))
+            }
+            DimensionKind::Integer { min, step, .. } => {
+                let len = self.len();
+                let step = (*step).max(1);
+                let value = scale_unit(unit, *min as f64, (min + len as i64 * step) as f64, method);
+                let index = ((value - *min as f64) / step as f64).floor().max(0.) as usize;
+                self.value(index.min(len - 1))
+            }
+            DimensionKind::Categorical(_) | DimensionKind::Boolean => {
+                let len = self.len();
+                self.value(((unit * len as f64).floor() as usize).min(len - 1))
+            }
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParamSet(pub BTreeMap<String, ParamValue>);
+
+impl ParamSet {
+    pub fn get(&self, name: &str) -> Option<&ParamValue> {
+        self.0.get(name)
+    }
+
+    pub fn get_f64(&self, name: &str) -> Option<f64> {
+        match self.0.get(name)? {
+            ParamValue::Float(value) => Some(*value),
+            ParamValue::Integer(value) => Some(*value as f64),
+            ParamValue::Boolean(value) => Some(if *value { 1. } else { 0. }),
+            ParamValue::Categorical(_) => None,
+        }
+    }
+
+    pub fn get_usize(&self, name: &str) -> Option<usize> {
+        match self.0.get(name)? {
+            ParamValue::Integer(value) if *value >= 0 => Some(*value as usize),
+            ParamValue::Float(value) if *value >= 0. => Some(value.round() as usize),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.0.get(name)? {
+            ParamValue::Categorical(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.0.get(name)? {
+            ParamValue::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, name: &str, value: ParamValue) {
+        self.0.insert(name.to_string(), value);
+    }
+
+    // Clé exacte et stable de la combinaison, les flottants étant comparés bit à bit
+    pub fn key(&self) -> String {
+        self.0
+            .iter()
+            .map(|(name, value)| match value {
+                ParamValue::Float(value) => format!("{}=f{:016x}", name, value.to_bits()),
+                ParamValue::Integer(value) => format!("{}=i{}", name, value),
+                ParamValue::Categorical(value) => format!("{}=c{:?}", name, value),
+                ParamValue::Boolean(value) => format!("{}=b{}", name, value),
+            })
+            .collect::<Vec<String>>()
+            .join(";")
+    }
+}
+
+#[derive(Clone)]
+pub enum Constraint {
+    GreaterThan(String, String),
+    GreaterOrEqual(String, String),
+    Custom(fn(&ParamSet) -> bool),
+}
+
+impl Constraint {
+    pub fn is_satisfied(&self, set: &ParamSet) -> bool {
+        match self {
+            Constraint::GreaterThan(a, b) => match (set.get_f64(a), set.get_f64(b)) {
+                (Some(a), Some(b)) => a > b,
+                _ => false,
+            },
+            Constraint::GreaterOrEqual(a, b) => match (set.get_f64(a), set.get_f64(b)) {
+                (Some(a), Some(b)) => a >= b,
+                _ => false,
+            },
+            Constraint::Custom(function) => function(set),
+        }
+    }
+}
+
+pub trait StrategyBuilder {
+    fn build(&self, params: &ParamSet) -> Option<Strategy>;
+}
+
+// Construit les stratégies à patterns depuis les dimensions tp, sl, risk et
+// klines_repetitions/klines_range (W, M) ou trend_size/counter_trend_size (BullReversal)
+#[derive(Clone, Copy)]
+pub struct PatternStrategyBuilder {
+    pub name: StrategyName,
+    pub start_money: f64,
+    pub market_type: MarketType,
+}
+
+impl PatternStrategyBuilder {
+    pub fn dimension_names(name: StrategyName) -> (&'static str, &'static str) {
+        match name {
+            StrategyName::BullReversal => ("trend_size", "counter_trend_size"),
+            _ => ("klines_repetitions", "klines_range"),
+        }
+    }
+
+    pub fn space(
+        name: StrategyName,
+        tp: ParamMultiplier<f64>,
+        sl: ParamMultiplier<f64>,
+        pattern_size: ParamMultiplier<usize>,
+        pattern_range: ParamMultiplier<usize>,
+        risk: ParamMultiplier<f64>,
+    ) -> ParamSpace {
+        let (size_name, range_name) = Self::dimension_names(name);
+        ParamSpace::new()
+            .float("tp", tp.min, tp.max, tp.step)
+            .float("sl", sl.min, sl.max, sl.step)
+            .integer(
+                size_name,
+                pattern_size.min as i64,
+                pattern_size.max as i64,
+                pattern_size.step as i64,
+            )
+            .integer(
+                range_name,
+                pattern_range.min as i64,
+                pattern_range.max as i64,
+                pattern_range.step as i64,
+            )
+            .float("risk", risk.min, risk.max, risk.step)
+    }
+}
+
+impl StrategyBuilder for PatternStrategyBuilder {
+    fn build(&self, params: &ParamSet) -> Option<Strategy> {
+        let (size_name, range_name) = Self::dimension_names(self.name);
+        let create = pattern_strategy_creator(self.name)?;
+        Some(create(
+            self.start_money,
+            params.get_f64("tp")?,
+            params.get_f64("sl")?,
+            params.get_usize(size_name)?,
+            params.get_usize(range_name)?,
+            params.get_f64("risk")?,
+            self.market_type,
+        ))
+    }
+}
+
+// Construit les stratégies de confluence depuis les dimensions tp, sl, risk, window,
+// required (règle AtLeast) et "{index}.{paramètre}" pour les composants, index partant de 0
+#[derive(Clone)]
+pub struct ConfluenceStrategyBuilder {
+    pub start_money: f64,
+    pub market_type: MarketType,
+    pub rule: ConfluenceRule,
+    pub components: Vec<PatternConfig>,
+}
+
+impl ConfluenceStrategyBuilder {
+    pub fn space(
+        tp: ParamMultiplier<f64>,
+        sl: ParamMultiplier<f64>,
+        window: ParamMultiplier<usize>,
+        risk: ParamMultiplier<f64>,
+        sweep: &BTreeMap<String, ParamMultiplier<usize>>,
+    ) -> ParamSpace {
+        let mut space = ParamSpace::new()
+            .float("tp", tp.min, tp.max, tp.step)
+            .float("sl", sl.min, sl.max, sl.step)
+            .integer(
+                "window",
+                window.min as i64,
+                window.max as i64,
+                window.step as i64,
+            )
+            .float("risk", risk.min, risk.max, risk.step);
+        for (name, range) in sweep {
+            space = space.integer(name, range.min as i64, range.max as i64, range.step as i64);
+        }
+        space
+    }
+}
+
+impl ConfluenceStrategyBuilder {
+    // Vrai si build applique la dimension name : "required" pour une règle AtLeast, ou
+    // "{index}.{paramètre}" pour un paramètre existant d'un composant
+    pub fn is_sweep_key(&self, name: &str) -> bool {
+        if name == "required" {
+            return matches!(self.rule, ConfluenceRule::AtLeast(_));
+        }
+        let Some((index, field)) = name.split_once('.') else {
+            return false;
+        };
+        index
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| self.components.get(index))
+            .is_some_and(|component| {
+                component
+                    .values()
+                    .iter()
+                    .any(|(component_field, _)| *component_field == field)
+            })
+    }
+}
+
+impl StrategyBuilder for ConfluenceStrategyBuilder {
+    fn build(&self, params: &ParamSet) -> Option<Strategy> {
+        let rule = match (self.rule, params.get_usize("required")) {
+            (ConfluenceRule::AtLeast(_), Some(required)) => ConfluenceRule::AtLeast(required),
+            (rule, _) => rule,
+        };
+        let mut patterns = vec![PatternConfig::Confluence {
+            rule,
+            window: params.get_usize("window")?,
+        }];
+        for (i, mut component) in self.components.iter().copied().enumerate() {
+            for name in component.values().iter().map(|(name, _)| *name) {
+                if let Some(value) = params.get_usize(&format!("{}.{}", i, name)) {
+                    component.set(name, value);
+                }
+            }
+            patterns.push(component);
+        }
+        Some(create_confluence_strategy(
+            self.start_money,
+            params.get_f64("tp")?,
+            params.get_f64("sl")?,
+            &patterns,
+            params.get_f64("risk")?,
+            self.market_type,
+        ))
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ParamSpace {
+    pub dimensions: Vec<Dimension>,
+    pub constraints: Vec<Constraint>,
+}
+
+impl ParamSpace {
+    pub fn new() -> Self {
+        ParamSpace::default()
+    }
+
+    pub fn dimension(mut self, name: &str, kind: DimensionKind) -> Self {
+        self.dimensions.push(Dimension {
+            name: name.to_string(),
+            kind,
+        });
+        self
+    }
+
+    pub fn float(self, name: &str, min: f64, max: f64, step: f64) -> Self {
+        self.dimension(name, DimensionKind::Float { min, max, step })
+    }
+
+    pub fn integer(self, name: &str, min: i64, max: i64, step: i64) -> Self {
+        self.dimension(name, DimensionKind::Integer { min, max, step })
+    }
+
+    pub fn categorical(self, name: &str, values: &[&str]) -> Self {
+        self.dimension(
+            name,
+            DimensionKind::Categorical(values.iter().map(|value| value.to_string()).collect()),
+        )
+    }
+
+    pub fn boolean(self, name: &str) -> Self {
+        self.dimension(name, DimensionKind::Boolean)
+    }
+
+    pub fn constraint(mut self, constraint: Constraint) -> Self {
+        self.constraints.push(constraint);
+        self
+    }
+
+    pub fn is_valid(&self, set: &ParamSet) -> bool {
+        self.constraints
+            .iter()
+            .all(|constraint| constraint.is_satisfied(set))
+    }
+
+    // Taille de la grille complète, avant application des contraintes
+    pub fn grid_size(&self) -> usize {
+        self.dimensions
+            .iter()
+            .map(|dimension| dimension.len())
+            .product()
+    }
+
+    // Toutes les combinaisons valides, la première dimension variant le moins vite
+    pub fn enumerate(&self) -> Vec<ParamSet> {
+        let lens: Vec<usize> = self
+            .dimensions
+            .iter()
+            .map(|dimension| dimension.len())
+            .collect();
+        if lens.contains(&0) {
+            return Vec::new();
+        }
+
+        let mut sets = Vec::new();
+        let mut indices = vec![0; self.dimensions.len()];
+        loop {
+            let mut set = ParamSet::default();
+            for (dimension, i) in self.dimensions.iter().zip(indices.iter()) {
+                set.insert(&dimension.name, dimension.value(*i));
+            }
+            if self.is_valid(&set) {
+                sets.push(set);
+            }
+
+            let mut d = indices.len();
+            loop {
+                if d == 0 {
+                    return sets;
+                }
+                d -= 1;
+                indices[d] += 1;
+                if indices[d] < lens[d] {
+                    break;
+                }
+                indices[d] = 0;
+            }
+        }
+    }
+
+    pub fn has_empty_dimension(&self) -> bool {
+        self.dimensions.iter().any(Dimension::is_empty)
+    }
+
+    pub fn from_unit(&self, unit: &[f64], method: SamplingMethod) -> Option<ParamSet> {
+        let mut set = ParamSet::default();
+        for (dimension, u) in self.dimensions.iter().zip(unit) {
+            set.insert(&dimension.name, dimension.from_unit(*u, method)?);
+        }
+        Some(set)
+    }
+
+    // Tire jusqu'à n combinaisons valides. Les tirages rejetés par les contraintes sont
+    // remplacés, dans la limite de dix passes.
+    pub fn sample(&self, n: usize, seed: u64, method: SamplingMethod) -> Vec<ParamSet> {
+        if self.has_empty_dimension() {
+            return Vec::new();
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut sets = Vec::with_capacity(n);
+        for _ in 0..10 {
+            if sets.len() >= n {
+                break;
+            }
+            for unit in unit_samples(n - sets.len(), self.dimensions.len(), method, &mut rng) {
+                match self.from_unit(&unit, method) {
+                    Some(set) if self.is_valid(&set) => sets.push(set),
+                    _ => {}
+                }
+            }
+        }
+        sets
+    }
+
+    pub fn instantiate(&self, sets: &[ParamSet], builder: &dyn StrategyBuilder) -> Vec<Strategy> {
+        sets.iter().filter_map(|set| builder.build(set)).collect()
+    }
+
+    pub fn create_strategies(&self, builder: &dyn StrategyBuilder) -> Vec<Strategy> {
+        self.instantiate(&self.enumerate(), builder)
+    }
+}
@@ -1,11 +1,19 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 
 use downcast_rs::DowncastSync;
 use downcast_rs::impl_downcast;
+use serde::{Deserialize, Serialize};
+
+use crate::confluence::ConfluenceRule;
+use crate::series::*;
 static mut _KLINE_TIME: i64 = 0;
 
-pub trait PatternParams: DowncastSync { fn get_params(&self) -> HashMap<String, String>; }
+pub trait PatternParams: DowncastSync {
+    fn get_params(&self) -> HashMap<String, String>;
+    fn to_config(&self) -> PatternConfig;
+}
 impl_downcast!(PatternParams);
 impl PatternParams for WPatternParams {  
     fn get_params(&self) -> HashMap<String, String> {
@@ -15,6 +23,12 @@ impl PatternParams for WPatternParams {
         map.insert(String::from("name"), self.name.to_string());
         map
     }
+    fn to_config(&self) -> PatternConfig {
+        PatternConfig::W {
+            klines_repetitions: self.klines_repetitions,
+            klines_range: self.klines_range,
+        }
+    }
 }
 impl PatternParams for MPatternParams {  
     fn get_params(&self) -> HashMap<String, String> {
@@ -24,6 +38,12 @@ impl PatternParams for MPatternParams {
         map.insert(String::from("name"), self.name.to_string());
         map
     }
+    fn to_config(&self) -> PatternConfig {
+        PatternConfig::M {
+            klines_repetitions: self.klines_repetitions,
+            klines_range: self.klines_range,
+        }
+    }
 }
 impl PatternParams for ReversalPatternParams {  
     fn get_params(&self) -> HashMap<String, String> {
@@ -33,14 +53,50 @@ impl PatternParams for ReversalPatternParams {
         map.insert(String::from("name"), self.name.to_string());
         map
     }
+    fn to_config(&self) -> PatternConfig {
+        PatternConfig::BullReversal {
+            trend_size: self.trend_size,
+            counter_trend_size: self.counter_trend_size,
+        }
+    }
+}
+impl PatternParams for ConfluenceParams {  
+    fn get_params(&self) -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        map.insert(String::from("rule"), self.rule.to_string());
+        map.insert(String::from("window"), self.window.to_string());
+        map.insert(String::from("name"), self.name.to_string());
+        map
+    }
+    fn to_config(&self) -> PatternConfig {
+        PatternConfig::Confluence {
+            rule: self.rule,
+            window: self.window,
+        }
+    }
+}
+impl PatternParams for EmaTrendParams {  
+    fn get_params(&self) -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        map.insert(String::from("period"), self.period.to_string());
+        map.insert(String::from("name"), self.name.to_string());
+        map
+    }
+    fn to_config(&self) -> PatternConfig {
+        PatternConfig::EmaTrend {
+            period: self.period,
+        }
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum PatternName {
     None,
     W,
     M,
-    BullReversal
+    BullReversal,
+    Confluence,
+    EmaTrend
 }
 
 impl fmt::Display for PatternName {
@@ -50,6 +106,141 @@ impl fmt::Display for PatternName {
             PatternName::W => write!(f, "W"),
             PatternName::M => write!(f, "M"),
             PatternName::BullReversal => write!(f, "Bull Reversal"),
+            PatternName::Confluence => write!(f, "Confluence"),
+            PatternName::EmaTrend => write!(f, "EMA Trend"),
+        }
+    }
+}
+
+// Forme sérialisable des paramètres de pattern : {"type": "W", "klines_repetitions": 3, "klines_range": 5}
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PatternConfig {
+    W {
+        klines_repetitions: usize,
+        klines_range: usize,
+    },
+    M {
+        klines_repetitions: usize,
+        klines_range: usize,
+    },
+    BullReversal {
+        trend_size: usize,
+        counter_trend_size: usize,
+    },
+    // Combine les patterns et conditions qui le suivent dans patterns_params
+    Confluence {
+        rule: ConfluenceRule,
+        window: usize,
+    },
+    // Condition : clôture au-dessus de l'EMA pour un long, en dessous pour un short
+    EmaTrend {
+        period: usize,
+    },
+}
+
+impl PatternConfig {
+    pub fn name(&self) -> PatternName {
+        match self {
+            PatternConfig::W { .. } => PatternName::W,
+            PatternConfig::M { .. } => PatternName::M,
+            PatternConfig::BullReversal { .. } => PatternName::BullReversal,
+            PatternConfig::Confluence { .. } => PatternName::Confluence,
+            PatternConfig::EmaTrend { .. } => PatternName::EmaTrend,
+        }
+    }
+
+    pub fn to_params(&self) -> Arc<dyn PatternParams> {
+        match *self {
+            PatternConfig::W { klines_repetitions, klines_range } => Arc::new(WPatternParams {
+                klines_repetitions,
+                klines_range,
+                name: PatternName::W,
+            }),
+            PatternConfig::M { klines_repetitions, klines_range } => Arc::new(MPatternParams {
+                klines_repetitions,
+                klines_range,
+                name: PatternName::M,
+            }),
+            PatternConfig::BullReversal { trend_size, counter_trend_size } => {
+                Arc::new(ReversalPatternParams {
+                    trend_size,
+                    counter_trend_size,
+                    name: PatternName::BullReversal,
+                })
+            }
+            PatternConfig::Confluence { rule, window } => Arc::new(ConfluenceParams {
+                rule,
+                window,
+                name: PatternName::Confluence,
+            }),
+            PatternConfig::EmaTrend { period } => Arc::new(EmaTrendParams {
+                period,
+                name: PatternName::EmaTrend,
+            }),
+        }
+    }
+
+    // Paramètres numériques, dans l'ordre de déclaration
+    pub fn values(&self) -> Vec<(&'static str, f64)> {
+        match *self {
+            PatternConfig::W { klines_repetitions, klines_range }
+            | PatternConfig::M { klines_repetitions, klines_range } => vec![
+                ("klines_repetitions", klines_repetitions as f64),
+                ("klines_range", klines_range as f64),
+            ],
+            PatternConfig::BullReversal { trend_size, counter_trend_size } => vec![
+                ("trend_size", trend_size as f64),
+                ("counter_trend_size", counter_trend_size as f64),
+            ],
+            PatternConfig::Confluence { rule, window } => match rule {
+                ConfluenceRule::AtLeast(required) => vec![
+                    ("window", window as f64),
+                    ("required", required as f64),
+                ],
+                _ => vec![("window", window as f64)],
+            },
+            PatternConfig::EmaTrend { period } => vec![("period", period as f64)],
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.values()
+            .into_iter()
+            .find(|(other, _)| *other == name)
+            .map(|(_, value)| value)
+    }
+
+    // Remplace un paramètre numérique, false si le pattern n'en a pas de ce nom
+    pub fn set(&mut self, name: &str, value: usize) -> bool {
+        match (self, name) {
+            (PatternConfig::W { klines_repetitions, .. }, "klines_repetitions")
+            | (PatternConfig::M { klines_repetitions, .. }, "klines_repetitions") => *klines_repetitions = value,
+            (PatternConfig::W { klines_range, .. }, "klines_range")
+            | (PatternConfig::M { klines_range, .. }, "klines_range") => *klines_range = value,
+            (PatternConfig::BullReversal { trend_size, .. }, "trend_size") => *trend_size = value,
+            (PatternConfig::BullReversal { counter_trend_size, .. }, "counter_trend_size") => *counter_trend_size = value,
+            (PatternConfig::Confluence { window, .. }, "window") => *window = value,
+            (PatternConfig::Confluence { rule: ConfluenceRule::AtLeast(required), .. }, "required") => *required = value,
+            (PatternConfig::EmaTrend { period }, "period") => *period = value,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl fmt::Display for PatternConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<String> = self
+            .values()
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        match self {
+            PatternConfig::Confluence { rule, .. } => {
+                write!(f, "{} {} {}", self.name(), rule, values.join(" "))
+            }
+            _ => write!(f, "{} {}", self.name(), values.join(" ")),
         }
     }
 }
@@ -99,39 +290,52 @@ pub struct MathKLine {
     pub taker_buy_quote_asset_volume: String,
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 struct TestParams {
     price: Option<f64>,
-    kline: Option<MathKLine>
+    kline: Option<usize>
 }
 
 struct TestFunction {
-    function: fn (MathKLine, Option<TestParams>) -> bool,
+    function: fn (&SeriesView, usize, Option<TestParams>) -> bool,
     params: Option<TestParams>,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct WPatternParams {
     pub klines_repetitions: usize,
     pub klines_range: usize,
     pub name: PatternName
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct MPatternParams {
     pub klines_repetitions: usize,
     pub klines_range: usize,
     pub name: PatternName
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct ReversalPatternParams {
     pub trend_size: usize,
     pub counter_trend_size: usize,
     pub name: PatternName
 }
 
-pub fn find_potential_w_pattern(vec: &[MathKLine], options: WPatternParams) -> Option<(WPattern, usize)>{
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct ConfluenceParams {
+    pub rule: ConfluenceRule,
+    pub window: usize,
+    pub name: PatternName
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct EmaTrendParams {
+    pub period: usize,
+    pub name: PatternName
+}
+
+pub fn find_potential_w_pattern(vec: SeriesView, options: WPatternParams) -> Option<(WPattern, usize)>{
     let n: usize = options.klines_repetitions;
     let start_index: usize;
     let second_v_index: usize;
@@ -139,14 +343,14 @@ pub fn find_potential_w_pattern(vec: &[MathKLine], options: WPatternParams) -> O
     let neckline_index: usize;
     let lower_price: f64;
     let neckline_price: f64;
-    let start_time = vec[0].open_time;
+    let start_time = vec.open_time[0];
     let end_time: i64 = 0;
 
     let is_down_test = vec![TestFunction{function: is_down, params: None}];
     let is_up_test = vec![TestFunction{function: is_up, params: None}];
 
     // Not enough KLines or downward trend
-    if vec.len() < n+options.klines_range || test_multiple_klines(&vec[0..n], n, &is_down_test).is_none() {
+    if vec.len() < n+options.klines_range || test_multiple_klines(vec.slice(0..n), n, &is_down_test).is_none() {
         return None;
     }
     
@@ -154,13 +358,13 @@ pub fn find_potential_w_pattern(vec: &[MathKLine], options: WPatternParams) -> O
     let downtrend_end = n-1;
     let first_v_test = vec![
         TestFunction{function: is_up, params: None},
-        TestFunction{function: is_not_breaking_price_downwards, params: Some(TestParams{price: Some(vec[downtrend_end].low), kline: None})}
+        TestFunction{function: is_not_breaking_price_downwards, params: Some(TestParams{price: Some(vec.low[downtrend_end]), kline: None})}
         ];
         
-    if let Some(result) = test_multiple_klines(&vec[n..n+n], n, &first_v_test) {
+    if let Some(result) = test_multiple_klines(vec.get(n..n+n)?, n, &first_v_test) {
         start_index = n + n - 1;
-        lower_price = vec[downtrend_end].low;
-        neckline_price = vec[start_index].high;
+        lower_price = vec.low[downtrend_end];
+        neckline_price = vec.high[start_index];
     } else {
         return None;
     };
@@ -177,7 +381,7 @@ pub fn find_potential_w_pattern(vec: &[MathKLine], options: WPatternParams) -> O
     let find_lower_kline_fast_condition = vec![
         TestFunction{function: is_breaking_price_upwards, params: Some(TestParams{price: Some(neckline_price), kline: None})}
         ];
-    if let Some(result) = find_kline(&vec[start_index..options.klines_range], 
+    if let Some(result) = find_kline(vec.get(start_index..options.klines_range)?, 
         &find_lower_kline_test,
         &find_lower_kline_failing_condition,
         &find_lower_kline_fast_condition) {
@@ -190,7 +394,7 @@ pub fn find_potential_w_pattern(vec: &[MathKLine], options: WPatternParams) -> O
     }
 
     // Find the continuation on upward trend + check if lower price breaks
-    if test_multiple_klines(&vec[neckline_index+1..neckline_index+1], 1, &is_up_test).is_none() {
+    if test_multiple_klines(vec.slice(neckline_index+1..neckline_index+1), 1, &is_up_test).is_none() {
         second_v_index = neckline_index + 1;
     } else {
         return None;
@@ -201,7 +405,7 @@ pub fn find_potential_w_pattern(vec: &[MathKLine], options: WPatternParams) -> O
     Some((WPattern { start_index, start_time, end_index: second_v_index, end_time, lower_price, neckline_price }, second_v_index))
 }
 
-pub fn find_trigger_w_pattern(vec: &[MathKLine], options: WPatternParams, potential_pattern: WPattern, second_v_index: usize) -> Option<WPattern>{
+pub fn find_trigger_w_pattern(vec: SeriesView, options: WPatternParams, potential_pattern: WPattern, second_v_index: usize) -> Option<WPattern>{
     let end_index: usize;
     let end_time: i64;
 
@@ -213,12 +417,12 @@ pub fn find_trigger_w_pattern(vec: &[MathKLine], options: WPatternParams, potent
     let find_lower_kline_fast_condition = vec![
         TestFunction{function: is_breaking_price_upwards, params: Some(TestParams{price: Some(potential_pattern.neckline_price), kline: None})}
         ];
-    if let Some(result) = find_kline(&vec[potential_pattern.start_index..options.klines_range], 
+    if let Some(result) = find_kline(vec.get(potential_pattern.start_index..options.klines_range)?, 
         &find_lower_kline_test,
         &find_lower_kline_failing_condition,
         &find_lower_kline_fast_condition) {
             end_index = result + second_v_index;
-            end_time = vec[end_index].close_time;
+            end_time = vec.close_time[end_index];
     } else {
         return None;
     };
@@ -233,7 +437,7 @@ pub fn find_trigger_w_pattern(vec: &[MathKLine], options: WPatternParams, potent
     })
 }
 
-pub fn find_w_pattern(vec: &[MathKLine], options: WPatternParams, potential_only: bool) -> Option<WPattern>{
+pub fn find_w_pattern(vec: SeriesView, options: WPatternParams, potential_only: bool) -> Option<WPattern>{
     if let Some((pattern, second_v_index)) = find_potential_w_pattern(vec, options) {
         if potential_only {
             return Some(pattern); 
@@ -244,7 +448,7 @@ pub fn find_w_pattern(vec: &[MathKLine], options: WPatternParams, potential_only
     return None;
 }
 
-pub fn find_potential_m_pattern(vec: &[MathKLine], options: MPatternParams) -> Option<(MPattern, usize)>{
+pub fn find_potential_m_pattern(vec: SeriesView, options: MPatternParams) -> Option<(MPattern, usize)>{
     let n: usize = options.klines_repetitions;
     let start_index: usize;
     let second_n_index: usize;
@@ -252,14 +456,14 @@ pub fn find_potential_m_pattern(vec: &[MathKLine], options: MPatternParams) -> O
     let neckline_index: usize;
     let higher_price: f64;
     let neckline_price: f64;
-    let start_time = vec[0].open_time;
+    let start_time = vec.open_time[0];
     let end_time: i64 = 0;
 
     let is_down_test = vec![TestFunction{function: is_down, params: None}];
     let is_up_test = vec![TestFunction{function: is_up, params: None}];
 
     // Not enough KLines or upward trend
-    if vec.len() < n+options.klines_range || test_multiple_klines(&vec[0..n], n, &is_up_test).is_none() {
+    if vec.len() < n+options.klines_range || test_multiple_klines(vec.slice(0..n), n, &is_up_test).is_none() {
         return None;
     }
     
@@ -267,12 +471,12 @@ pub fn find_potential_m_pattern(vec: &[MathKLine], options: MPatternParams) -> O
     let uptrend_end = n-1;
     let first_n_test = vec![
         TestFunction{function: is_down, params: None},
-        TestFunction{function: is_not_breaking_price_upwards, params: Some(TestParams{price: Some(vec[uptrend_end].high), kline: None})}
+        TestFunction{function: is_not_breaking_price_upwards, params: Some(TestParams{price: Some(vec.high[uptrend_end]), kline: None})}
         ];
-    if let Some(result) = test_multiple_klines(&vec[n..n+n], n, &first_n_test) {
+    if let Some(result) = test_multiple_klines(vec.get(n..n+n)?, n, &first_n_test) {
         start_index = n + n - 1;
-        higher_price = vec[uptrend_end].high;
-        neckline_price = vec[start_index].low;
+        higher_price = vec.high[uptrend_end];
+        neckline_price = vec.low[start_index];
     } else {
         return None;
     };
@@ -290,7 +494,7 @@ pub fn find_potential_m_pattern(vec: &[MathKLine], options: MPatternParams) -> O
     let find_higher_kline_fast_condition = vec![
         TestFunction{function: is_breaking_price_downwards, params: Some(TestParams{price: Some(neckline_price), kline: None})}
         ];
-    if let Some(result) = find_kline(&vec[start_index..options.klines_range], 
+    if let Some(result) = find_kline(vec.get(start_index..options.klines_range)?, 
         &find_higher_kline_test,
         &find_higher_kline_failing_condition,
         &find_higher_kline_fast_condition) {
@@ -303,7 +507,7 @@ pub fn find_potential_m_pattern(vec: &[MathKLine], options: MPatternParams) -> O
     }
 
     // Check if the next kline is downward
-    if test_multiple_klines(&vec[neckline_index+1..neckline_index+1], 1, &is_down_test).is_none() {
+    if test_multiple_klines(vec.slice(neckline_index+1..neckline_index+1), 1, &is_down_test).is_none() {
         second_n_index = neckline_index + 1;
     } else {
         return None;
@@ -315,7 +519,7 @@ pub fn find_potential_m_pattern(vec: &[MathKLine], options: MPatternParams) -> O
     Some((MPattern { start_index, start_time, end_index: second_n_index, end_time, higher_price, neckline_price }, second_n_index))
 }
 
-pub fn find_trigger_m_pattern(vec: &[MathKLine], options: MPatternParams, potential_pattern: MPattern, second_n_index: usize) -> Option<MPattern>{
+pub fn find_trigger_m_pattern(vec: SeriesView, options: MPatternParams, potential_pattern: MPattern, second_n_index: usize) -> Option<MPattern>{
     let end_index: usize;
     let end_time: i64;
 
@@ -327,12 +531,12 @@ pub fn find_trigger_m_pattern(vec: &[MathKLine], options: MPatternParams, potent
     let find_higher_kline_fast_condition = vec![
         TestFunction{function: is_breaking_price_downwards, params: Some(TestParams{price: Some(potential_pattern.neckline_price), kline: None})}
         ];
-    if let Some(result) = find_kline(&vec[potential_pattern.start_index..options.klines_range], 
+    if let Some(result) = find_kline(vec.get(potential_pattern.start_index..options.klines_range)?, 
         &find_higher_kline_test,
         &find_higher_kline_failing_condition,
         &find_higher_kline_fast_condition) {
             end_index = result + second_n_index;
-            end_time = vec[end_index].close_time;
+            end_time = vec.close_time[end_index];
     } else {
         return None;
     };
@@ -347,7 +551,7 @@ pub fn find_trigger_m_pattern(vec: &[MathKLine], options: MPatternParams, potent
     })
 }
 
-pub fn find_m_pattern(vec: &[MathKLine], options: MPatternParams, potential_only: bool) -> Option<MPattern>{
+pub fn find_m_pattern(vec: SeriesView, options: MPatternParams, potential_only: bool) -> Option<MPattern>{
     if let Some((pattern, second_v_index)) = find_potential_m_pattern(vec, options) {
         if potential_only {
             return Some(pattern); 
@@ -358,7 +562,7 @@ pub fn find_m_pattern(vec: &[MathKLine], options: MPatternParams, potential_only
     return None;
 }
 
-pub fn find_bull_reversal(vec: &[MathKLine], options: ReversalPatternParams, potential_only: bool) -> Option<ReversalPattern>{
+pub fn find_bull_reversal(vec: SeriesView, options: ReversalPatternParams, potential_only: bool) -> Option<ReversalPattern>{
     let start_index;
     let start_time;
     let end_index;
@@ -371,32 +575,32 @@ pub fn find_bull_reversal(vec: &[MathKLine], options: ReversalPatternParams, pot
     let is_down_test = vec![TestFunction{function: is_down, params: None}];
     let is_up_test = vec![TestFunction{function: is_up, params: None}];
 
-    if let Some(result) = test_multiple_klines(&vec[0..], options.trend_size, &is_down_test) {
+    if let Some(result) = test_multiple_klines(vec, options.trend_size, &is_down_test) {
         start_index = 0;
-        start_time = vec[0].open_time;
+        start_time = vec.open_time[0];
         trend_end_index = result;
-        peak_price = vec[result].close;
+        peak_price = vec.close[result];
     } else {
         return None;
     }
-    if let Some(result) = test_multiple_klines(&vec[trend_end_index..], options.counter_trend_size, &is_up_test) {
+    if let Some(result) = test_multiple_klines(vec.from(trend_end_index), options.counter_trend_size, &is_up_test) {
         end_index = result + trend_end_index;
-        end_time = vec[end_index].close_time;
-        end_price = vec[end_index].close;
+        end_time = vec.close_time[end_index];
+        end_price = vec.close[end_index];
     } else {
         return None;
     }
     Some(ReversalPattern { start_index, start_time, end_index, end_time, peak_price, end_price })
 }
 
-fn test_multiple_klines(vec: &[MathKLine], repetitions: usize, tests: &[TestFunction]) -> Option<usize> {
+fn test_multiple_klines(vec: SeriesView, repetitions: usize, tests: &[TestFunction]) -> Option<usize> {
     let mut tests_passed = 0;
     let mut klines_ok = 0;
 
-    for (i, item) in vec.iter().enumerate() {
+    for i in 0..vec.len() {
         tests_passed = 0;
         for test in tests {
-            if (test.function)(item.clone(), test.params.clone()) {
+            if (test.function)(&vec, i, test.params) {
                 tests_passed += 1;
                 if tests_passed >= tests.len() {
                     klines_ok += 1;
@@ -413,23 +617,23 @@ fn test_multiple_klines(vec: &[MathKLine], repetitions: usize, tests: &[TestFunc
     None
 }
 
-fn find_kline(vec: &[MathKLine], tests: &[TestFunction], failing_conditions: &[TestFunction], early_conditions: &[TestFunction]) -> Option<usize> {
+fn find_kline(vec: SeriesView, tests: &[TestFunction], failing_conditions: &[TestFunction], early_conditions: &[TestFunction]) -> Option<usize> {
     let mut tests_passed = 0;
     let mut best_kline_index = 0;
 
-    for (i, item) in vec.iter().enumerate() {
+    for i in 0..vec.len() {
         for constraint in failing_conditions {
-            if (constraint.function)(item.clone(), constraint.params.clone()) {
+            if (constraint.function)(&vec, i, constraint.params) {
                 return None;
             }
         }
 
         for constraint in early_conditions {
-            if (constraint.function)(item.clone(), constraint.params.clone()) {
+            if (constraint.function)(&vec, i, constraint.params) {
                 for test in tests {
-                    let mut params = test.params.clone().unwrap();
-                    params.kline = Some(vec[best_kline_index].clone());
-                    if (test.function)(item.clone(), Some(params)) {
+                    let mut params = test.params.unwrap_or(TestParams{price: None, kline: None});
+                    params.kline = Some(best_kline_index);
+                    if (test.function)(&vec, i, Some(params)) {
                         best_kline_index = i;
                     }
                 }
@@ -438,9 +642,9 @@ fn find_kline(vec: &[MathKLine], tests: &[TestFunction], failing_conditions: &[T
         }
 
         for test in tests {
-            let mut params = test.params.clone().unwrap();
-            params.kline = Some(vec[best_kline_index].clone());
-            if (test.function)(item.clone(), Some(params)) {
+            let mut params = test.params.unwrap_or(TestParams{price: None, kline: None});
+            params.kline = Some(best_kline_index);
+            if (test.function)(&vec, i, Some(params)) {
                 best_kline_index = i;
             }
         }
@@ -448,36 +652,41 @@ fn find_kline(vec: &[MathKLine], tests: &[TestFunction], failing_conditions: &[T
     return Some(best_kline_index);
 }
 
-fn is_up(kline: MathKLine, _: Option<TestParams>) -> bool {
-    kline.close > kline.open
+// Prix de référence d'un test, le test échoue s'il est absent
+fn test_price(params: Option<TestParams>) -> Option<f64> {
+    params?.price
+}
+
+fn is_up(vec: &SeriesView, i: usize, _: Option<TestParams>) -> bool {
+    vec.is_up(i)
 }
 
-fn is_down(kline: MathKLine, _: Option<TestParams>) -> bool {
-    kline.close < kline.open
+fn is_down(vec: &SeriesView, i: usize, _: Option<TestParams>) -> bool {
+    vec.is_down(i)
 }
 
-fn is_breaking_price_upwards(kline: MathKLine, params: Option<TestParams>) -> bool {
-    kline.high > params.unwrap().price.unwrap()
+fn is_breaking_price_upwards(vec: &SeriesView, i: usize, params: Option<TestParams>) -> bool {
+    test_price(params).is_some_and(|price| vec.high[i] > price)
 }
 
-fn is_breaking_price_downwards(kline: MathKLine, params: Option<TestParams>) -> bool {
-    kline.low < params.unwrap().price.unwrap()
+fn is_breaking_price_downwards(vec: &SeriesView, i: usize, params: Option<TestParams>) -> bool {
+    test_price(params).is_some_and(|price| vec.low[i] < price)
 }
 
-fn is_not_breaking_price_upwards(kline: MathKLine, params: Option<TestParams>) -> bool {
-    !(kline.high > params.unwrap().price.unwrap())
+fn is_not_breaking_price_upwards(vec: &SeriesView, i: usize, params: Option<TestParams>) -> bool {
+    test_price(params).is_some_and(|price| !(vec.high[i] > price))
 }
 
-fn is_not_breaking_price_downwards(kline: MathKLine, params: Option<TestParams>) -> bool {
-    !(kline.low < params.unwrap().price.unwrap())
+fn is_not_breaking_price_downwards(vec: &SeriesView, i: usize, params: Option<TestParams>) -> bool {
+    test_price(params).is_some_and(|price| !(vec.low[i] < price))
 }
 
-fn is_higher_than(kline: MathKLine, params: Option<TestParams>) -> bool {
-    kline.close > params.unwrap().price.unwrap()
+fn is_higher_than(vec: &SeriesView, i: usize, params: Option<TestParams>) -> bool {
+    test_price(params).is_some_and(|price| vec.close[i] > price)
 }
 
-fn is_lower_than(kline: MathKLine, params: Option<TestParams>) -> bool {
-    kline.close < params.unwrap().price.unwrap()
+fn is_lower_than(vec: &SeriesView, i: usize, params: Option<TestParams>) -> bool {
+    test_price(params).is_some_and(|price| vec.close[i] < price)
 }
 
 
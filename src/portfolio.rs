@@ -0,0 +1,256 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::account::*;
+use crate::backtest::*;
+use crate::benchmark::*;
+use crate::error::Result;
+use crate::patterns::*;
+use crate::series::*;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SymbolResult {
+    pub symbol: String,
+    pub result: StrategyResult,
+    // Capital réalisé par ce symbole à chaque kline alignée
+    pub equity_curve: Vec<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PortfolioResult {
+    pub aggregate: StrategyResult,
+    pub symbols: Vec<SymbolResult>,
+    // Matrice de corrélation des variations de capital, dans l'ordre de `symbols`
+    pub correlations: Vec<Vec<f64>>,
+}
+
+pub struct PortfolioBacktester {
+    symbols: Vec<String>,
+    klines_data: Vec<Arc<Vec<MathKLine>>>,
+    // Séries des klines alignées, construites une fois par symbole
+    series: Vec<PriceSeries>,
+    strategies: Vec<Strategy>,
+    results: Vec<PortfolioResult>,
+    portfolio_limits: PortfolioLimits,
+    cost_model: CostModel,
+}
+
+impl PortfolioBacktester {
+    pub fn new(klines_data: HashMap<String, Arc<Vec<MathKLine>>>) -> Self {
+        let mut symbols: Vec<String> = klines_data.keys().cloned().collect();
+        symbols.sort();
+        let series: Vec<Arc<Vec<MathKLine>>> = symbols
+            .iter()
+            .map(|symbol| klines_data[symbol].clone())
+            .collect();
+
+        let klines_data = Self::align(&series);
+        PortfolioBacktester {
+            symbols,
+            series: klines_data
+                .iter()
+                .map(|klines| PriceSeries::from_klines(klines))
+                .collect(),
+            klines_data,
+            strategies: Vec::new(),
+            results: Vec::new(),
+            portfolio_limits: PortfolioLimits::unlimited(),
+            cost_model: CostModel::default(),
+        }
+    }
+
+    // Ne garde que les klines dont l'open_time est présent pour tous les symboles
+    fn align(series: &[Arc<Vec<MathKLine>>]) -> Vec<Arc<Vec<MathKLine>>> {
+        let mut common: Option<BTreeSet<i64>> = None;
+        for klines in series {
+            let times: BTreeSet<i64> = klines.iter().map(|kline| kline.open_time).collect();
+            common = Some(match common {
+                Some(common) => common.intersection(&times).cloned().collect(),
+                None => times,
+            });
+        }
+        let common = common.unwrap_or_default();
+
+        series
+            .iter()
+            .map(|klines| {
+                Arc::new(
+                    klines
+                        .iter()
+                        .filter(|kline| common.contains(&kline.open_time))
+                        .cloned()
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    pub fn start(&mut self) -> Result<&mut Self> {
+        for strategy in self.strategies.clone().iter_mut() {
+            let result = self.run_strategy(strategy)?;
+            self.results.push(result);
+        }
+        Ok(self)
+    }
+
+    fn run_strategy(&self, strategy: &mut Strategy) -> Result<PortfolioResult> {
+        let start_money = strategy.1.money;
+        let mut books: Vec<TradeBook> = self
+            .klines_data
+            .iter()
+            .zip(self.series.iter())
+            .map(|(klines, series)| {
+                Ok(TradeBook::new(strategy.0(
+                    &**klines,
+                    series.view(),
+                    None,
+                    strategy.1,
+                    strategy.2.clone(),
+                    false,
+                )?))
+            })
+            .collect::<Result<_>>()?;
+
+        let mut account = Account::new(start_money, strategy.1.market_type, self.portfolio_limits);
+        account.costs = self.cost_model;
+        let len = self.series.first().map_or(0, |series| series.len());
+        for i in 0..len {
+            let bars: Vec<Bar> = self
+                .series
+                .iter()
+                .zip(self.klines_data.iter())
+                .map(|(series, klines)| Bar::new(series.view(), &**klines, i))
+                .collect();
+            if !account.process_bars(&mut books, &bars) {
+                break;
+            }
+        }
+        strategy.1.money = account.money;
+
+        let mut symbols = Vec::new();
+        let mut all_trades = Vec::new();
+        for (b, book) in books.into_iter().enumerate() {
+            let equity_curve = equity_curve(self.series[b].view(), &book.trades, start_money);
+            let mut symbol_strategy = strategy.clone();
+            symbol_strategy.1.money = *equity_curve.last().unwrap_or(&start_money);
+
+            let mut closed: Vec<&Trade> = book
+                .trades
+                .iter()
+                .filter(|trade| matches!(trade.status, Status::Closed(_)))
+                .collect();
+            closed.sort_by_key(|trade| trade.close_time);
+            let mut money = start_money;
+            let money_evolution = closed
+                .iter()
+                .map(|trade| {
+                    money += trade.realized_pnl();
+                    money
+                })
+                .collect();
+
+            symbols.push(SymbolResult {
+                symbol: self.symbols[b].clone(),
+                result: StrategyResult::from_trades(
+                    start_money,
+                    symbol_strategy.1,
+                    &symbol_strategy.2,
+                    &book.trades,
+                    money_evolution,
+                ),
+                equity_curve,
+            });
+            all_trades.extend(book.trades);
+        }
+
+        let correlations = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                symbols
+                    .iter()
+                    .enumerate()
+                    .map(|(j, b)| {
+                        // Une courbe est toujours corrélée à elle-même, même plate
+                        if i == j {
+                            return 1.;
+                        }
+                        pearson_correlation(&changes(&a.equity_curve), &changes(&b.equity_curve))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(PortfolioResult {
+            aggregate: StrategyResult::from_trades(
+                start_money,
+                strategy.1,
+                &strategy.2,
+                &all_trades,
+                account.money_evolution,
+            ),
+            symbols,
+            correlations,
+        })
+    }
+
+    pub fn set_portfolio_limits(&mut self, limits: PortfolioLimits) -> &mut Self {
+        self.portfolio_limits = limits;
+        self
+    }
+
+    pub fn set_cost_model(&mut self, cost_model: CostModel) -> &mut Self {
+        self.cost_model = cost_model;
+        self
+    }
+
+    pub fn add_strategy(&mut self, strategy: Strategy) -> &mut Self {
+        self.strategies.push(strategy);
+        self
+    }
+
+    pub fn add_strategies(&mut self, strategies: &mut Vec<Strategy>) -> &mut Self {
+        self.strategies.append(strategies);
+        self
+    }
+
+    pub fn get_symbols(&self) -> &Vec<String> {
+        &self.symbols
+    }
+
+    pub fn get_aligned_klines(&self, symbol: &str) -> Option<Arc<Vec<MathKLine>>> {
+        let index = self.symbols.iter().position(|s| s == symbol)?;
+        Some(self.klines_data[index].clone())
+    }
+
+    pub fn get_results(&self) -> Vec<PortfolioResult> {
+        self.results.clone()
+    }
+}
+
+fn changes(curve: &[f64]) -> Vec<f64> {
+    curve.windows(2).map(|w| w[1] - w[0]).collect()
+}
+
+pub fn pearson_correlation(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len().min(b.len());
+    if n < 2 {
+        return 0.;
+    }
+    let mean_a = a[..n].iter().sum::<f64>() / n as f64;
+    let mean_b = b[..n].iter().sum::<f64>() / n as f64;
+    let mut covariance = 0.;
+    let mut variance_a = 0.;
+    let mut variance_b = 0.;
+    for i in 0..n {
+        covariance += (a[i] - mean_a) * (b[i] - mean_b);
+        variance_a += (a[i] - mean_a).powi(2);
+        variance_b += (b[i] - mean_b).powi(2);
+    }
+    if variance_a == 0. || variance_b == 0. {
+        return 0.;
+    }
+    covariance / (variance_a.sqrt() * variance_b.sqrt())
+}
@@ -0,0 +1,163 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::backtest::StrategyName;
+use crate::error::{Error, Result};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProgressEvent {
+    StrategyStarted {
+        id: Option<usize>,
+        index: usize,
+        total: usize,
+        name: StrategyName,
+    },
+    Percent {
+        id: Option<usize>,
+        percent: f32,
+        eta: Option<Duration>,
+    },
+    TradesFound {
+        id: Option<usize>,
+        index: usize,
+        trades: usize,
+    },
+    StrategyFinished {
+        id: Option<usize>,
+        index: usize,
+        total: usize,
+        // Résultat repris de la base plutôt que recalculé
+        from_store: bool,
+    },
+    Finished {
+        id: Option<usize>,
+        completed: usize,
+        cancelled: bool,
+    },
+}
+
+// Partagé entre l'appelant et le backtester, annule le run au prochain point de contrôle
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub struct ProgressTracker {
+    sender: Option<Sender<ProgressEvent>>,
+    cancellation: CancellationToken,
+    id: Option<usize>,
+    total: usize,
+    current: usize,
+    started: Instant,
+}
+
+impl ProgressTracker {
+    pub fn new(
+        sender: Option<Sender<ProgressEvent>>,
+        cancellation: CancellationToken,
+        id: Option<usize>,
+        total: usize,
+    ) -> Self {
+        ProgressTracker {
+            sender,
+            cancellation,
+            id,
+            total,
+            current: 0,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    pub fn check_cancelled(&self) -> Result<()> {
+        self.cancellation.check()
+    }
+
+    pub fn strategy_started(&mut self, index: usize, name: StrategyName) {
+        self.current = index;
+        self.send(ProgressEvent::StrategyStarted {
+            id: self.id,
+            index,
+            total: self.total,
+            name,
+        });
+    }
+
+    // fraction : avancement de la stratégie courante, entre 0 et 1
+    pub fn report(&self, fraction: f32) {
+        if self.sender.is_none() || self.total == 0 {
+            return;
+        }
+        let done = (self.current as f32 + fraction.clamp(0., 1.)) / self.total as f32;
+        let eta = if done > 0. {
+            Some(self.started.elapsed().mul_f32((1. - done) / done))
+        } else {
+            None
+        };
+        self.send(ProgressEvent::Percent {
+            id: self.id,
+            percent: done * 100.,
+            eta,
+        });
+    }
+
+    pub fn trades_found(&self, trades: usize) {
+        self.send(ProgressEvent::TradesFound {
+            id: self.id,
+            index: self.current,
+            trades,
+        });
+    }
+
+    pub fn strategy_finished(&self, from_store: bool) {
+        self.send(ProgressEvent::StrategyFinished {
+            id: self.id,
+            index: self.current,
+            total: self.total,
+            from_store,
+        });
+        self.report(1.);
+    }
+
+    pub fn finished(&self, completed: usize) {
+        self.send(ProgressEvent::Finished {
+            id: self.id,
+            completed,
+            cancelled: self.is_cancelled(),
+        });
+    }
+
+    fn send(&self, event: ProgressEvent) {
+        // Le récepteur peut avoir été fermé, le backtest continue sans suivi
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(event);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::*;
+use crate::metrics::*;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Objective {
+    pub metric: Metric,
+    // Poids dans le score composite, sans effet sur le front de Pareto
+    pub weight: f64,
+}
+
+impl Objective {
+    pub fn new(metric: Metric, weight: f64) -> Self {
+        Objective { metric, weight }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Filter {
+    MinTrades(usize),
+    MaxDrawdown(f64),
+    MinValue(Metric, f64),
+    MaxValue(Metric, f64),
+}
+
+impl Filter {
+    pub fn accepts(&self, result: &StrategyResult) -> bool {
+        match self {
+            Filter::MinTrades(min) => result.total_closed >= *min,
+            Filter::MaxDrawdown(max) => Metric::MaxDrawdown.value(result) <= *max,
+            Filter::MinValue(metric, min) => metric.value(result) >= *min,
+            Filter::MaxValue(metric, max) => metric.value(result) <= *max,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RankedResult {
+    // Position du résultat dans le vecteur d'entrée
+    pub index: usize,
+    // 0 pour le front de Pareto, 1 pour le front obtenu une fois le premier retiré, etc.
+    pub dominance_level: usize,
+    pub score: f64,
+    pub values: Vec<f64>,
+    pub result: StrategyResult,
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct Ranking {
+    pub objectives: Vec<Objective>,
+    pub filters: Vec<Filter>,
+}
+
+impl Ranking {
+    pub fn new() -> Self {
+        Ranking::default()
+    }
+
+    pub fn add_objective(&mut self, metric: Metric, weight: f64) -> &mut Self {
+        self.objectives.push(Objective::new(metric, weight));
+        self
+    }
+
+    pub fn add_filter(&mut self, filter: Filter) -> &mut Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn accepts(&self, result: &StrategyResult) -> bool {
+        self.filters.iter().all(|filter| filter.accepts(result))
+    }
+
+    // Classe les résultats qui passent les filtres par niveau de dominance puis par score composite
+    pub fn rank(&self, results: &[StrategyResult]) -> Vec<RankedResult> {
+        let kept: Vec<usize> = (0..results.len())
+            .filter(|i| self.accepts(&results[*i]))
+            .collect();
+        let scores: Vec<Vec<f64>> = kept
+            .iter()
+            .map(|i| {
+                self.objectives
+                    .iter()
+                    .map(|objective| objective.metric.score(&results[*i]))
+                    .collect()
+            })
+            .collect();
+
+        let levels = dominance_levels(&scores);
+        let composite = self.composite_scores(&scores);
+
+        let mut ranked: Vec<RankedResult> = kept
+            .iter()
+            .enumerate()
+            .map(|(k, i)| RankedResult {
+                index: *i,
+                dominance_level: levels[k],
+                score: composite[k],
+                values: self
+                    .objectives
+                    .iter()
+                    .map(|objective| objective.metric.value(&results[*i]))
+                    .collect(),
+                result: results[*i].clone(),
+            })
+            .collect();
+        ranked.sort_by(|a, b| {
+            a.dominance_level
+                .cmp(&b.dominance_level)
+                .then(b.score.total_cmp(&a.score))
+        });
+        ranked
+    }
+
+    pub fn pareto_front(&self, results: &[StrategyResult]) -> Vec<RankedResult> {
+        self.rank(results)
+            .into_iter()
+            .filter(|ranked| ranked.dominance_level == 0)
+            .collect()
+    }
+
+    // Somme pondérée des scores ramenés entre 0 et 1 sur l'ensemble des résultats retenus
+    fn composite_scores(&self, scores: &[Vec<f64>]) -> Vec<f64> {
+        let total_weight: f64 = self.objectives.iter().map(|o| o.weight.abs()).sum();
+        let mut composite = vec![0.; scores.len()];
+        if total_weight == 0. {
+            return composite;
+        }
+
+        for (o, objective) in self.objectives.iter().enumerate() {
+            let finite = scores.iter().map(|s| s[o]).filter(|s| s.is_finite());
+            let min = finite.clone().fold(f64::INFINITY, f64::min);
+            let max = finite.fold(f64::NEG_INFINITY, f64::max);
+            for (k, s) in scores.iter().enumerate() {
+                let normalized = if !s[o].is_finite() {
+                    if s[o] > 0. {
+                        1.
+                    } else {
+                        0.
+                    }
+                } else if max > min {
+                    (s[o] - min) / (max - min)
+                } else {
+                    1.
+                };
+                composite[k] += objective.weight * normalized / total_weight;
+            }
+        }
+        composite
+    }
+}
+
+// a domine b s'il est au moins aussi bon sur chaque objectif et strictement meilleur sur l'un d'eux
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x >= y) && a.iter().zip(b).any(|(x, y)| x > y)
+}
+
+// Tri non dominé : niveau 0 pour les points qu'aucun autre ne domine, et ainsi de suite
+pub fn dominance_levels(scores: &[Vec<f64>]) -> Vec<usize> {
+    let n = scores.len();
+    let mut dominated_by = vec![0; n];
+    let mut dominating: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if dominates(&scores[i], &scores[j]) {
+                dominating[i].push(j);
+                dominated_by[j] += 1;
+            } else if dominates(&scores[j], &scores[i]) {
+                dominating[j].push(i);
+                dominated_by[i] += 1;
+            }
+        }
+    }
+
+    let mut levels = vec![0; n];
+    let mut front: Vec<usize> = (0..n).filter(|i| dominated_by[*i] == 0).collect();
+    let mut level = 0;
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for i in front {
+            levels[i] = level;
+            for j in dominating[i].iter() {
+                dominated_by[*j] -= 1;
+                if dominated_by[*j] == 0 {
+                    next.push(*j);
+                }
+            }
+        }
+        front = next;
+        level += 1;
+    }
+    levels
+}
@@ -0,0 +1,458 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write;
+
+use chrono::{TimeZone, Utc};
+
+use crate::backtest::*;
+use crate::error::Result;
+use crate::metrics::*;
+
+const CHART_WIDTH: f64 = 800.;
+const CHART_HEIGHT: f64 = 240.;
+const CHART_MARGIN: f64 = 40.;
+const HISTOGRAM_BINS: usize = 20;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 20px; color: #222; }
+table { border-collapse: collapse; margin-bottom: 20px; font-size: 13px; }
+th, td { border: 1px solid #ccc; padding: 3px 8px; text-align: right; }
+th { background: #eee; cursor: pointer; }
+tr:target { background: #ffe58a; }
+svg { background: #fafafa; border: 1px solid #ddd; margin-bottom: 10px; }
+.win { color: #1a7f37; } .lost { color: #c62828; }
+";
+
+// Tri des tableaux au clic sur un en-tête, sur la valeur numérique si possible
+const SCRIPT: &str = "
+document.querySelectorAll('table.sortable th').forEach(function (th, column) {
+  th.addEventListener('click', function () {
+    var body = th.closest('table').tBodies[0];
+    var ascending = th.dataset.order !== 'asc';
+    th.dataset.order = ascending ? 'asc' : 'desc';
+    Array.from(body.rows).sort(function (a, b) {
+      var x = a.cells[column].dataset.value || a.cells[column].textContent;
+      var y = b.cells[column].dataset.value || b.cells[column].textContent;
+      var nx = parseFloat(x), ny = parseFloat(y);
+      var order = isNaN(nx) || isNaN(ny) ? x.localeCompare(y) : nx - ny;
+      return ascending ? order : -order;
+    }).forEach(function (row) { body.appendChild(row); });
+  });
+});
+";
+
+pub struct HtmlReport {
+    title: String,
+    results: Vec<StrategyResult>,
+    metric: Metric,
+    // Nombre de meilleurs résultats détaillés avec graphiques et liste des trades
+    detailed: usize,
+}
+
+impl HtmlReport {
+    pub fn new(title: &str) -> Self {
+        HtmlReport {
+            title: title.to_string(),
+            results: Vec::new(),
+            metric: Metric::FinalMoney,
+            detailed: 10,
+        }
+    }
+
+    pub fn add_results(&mut self, results: &[StrategyResult]) -> &mut Self {
+        self.results.extend_from_slice(results);
+        self
+    }
+
+    pub fn set_metric(&mut self, metric: Metric) -> &mut Self {
+        self.metric = metric;
+        self
+    }
+
+    pub fn set_detailed(&mut self, detailed: usize) -> &mut Self {
+        self.detailed = detailed;
+        self
+    }
+
+    pub fn write(&self, path: &str) -> Result<()> {
+        Ok(File::create(path)?.write_all(self.render().as_bytes())?)
+    }
+
+    pub fn render(&self) -> String {
+        let mut order: Vec<usize> = (0..self.results.len()).collect();
+        order.sort_by(|a, b| {
+            self.metric
+                .score(&self.results[*b])
+                .total_cmp(&self.metric.score(&self.results[*a]))
+        });
+
+        let mut html = String::new();
+        writeln!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>",
+            escape(&self.title),
+            STYLE
+        )
+        .unwrap();
+        writeln!(html, "<h1>{}</h1>", escape(&self.title)).unwrap();
+        self.render_summary(&mut html, &order);
+        self.render_results_table(&mut html, &order);
+        for (rank, index) in order.iter().take(self.detailed).enumerate() {
+            render_detail(&mut html, rank, &self.results[*index]);
+        }
+        writeln!(html, "<script>{}</script>\n</body>\n</html>", SCRIPT).unwrap();
+        html
+    }
+
+    fn render_summary(&self, html: &mut String, order: &[usize]) {
+        let profitable = self
+            .results
+            .iter()
+            .filter(|result| result.final_money > result.start_money)
+            .count();
+        let trades: usize = self.results.iter().map(|result| result.total_closed).sum();
+        html.push_str("<h2>Summary</h2>\n<table>\n");
+        let mut row = |label: &str, value: String| {
+            writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", label, value).unwrap();
+        };
+        row("Strategies tested", self.results.len().to_string());
+        row("Profitable strategies", profitable.to_string());
+        row("Closed trades", trades.to_string());
+        row("Ranked by", self.metric.to_string());
+        if let Some(best) = order.first().map(|i| &self.results[*i]) {
+            row(
+                "Best strategy",
+                format!("<a href=\"#result-0\">{}</a>", escape(&describe(best))),
+            );
+            row("Best final money", format!("{:.2}", best.final_money));
+            row("Best return", format!("{:.2} %", total_return(best) * 100.));
+        }
+        html.push_str("</table>\n");
+    }
+
+    fn render_results_table(&self, html: &mut String, order: &[usize]) {
+        html.push_str("<h2>Results</h2>\n<table class=\"sortable\">\n<thead><tr>");
+        for header in [
+            "#",
+            "Strategy",
+            "Parameters",
+            "Final money",
+            "Return %",
+            "Max drawdown %",
+            "Win ratio %",
+            "Efficiency %",
+            "Closed",
+            "Not closed",
+            "Ignored",
+            "RR",
+        ] {
+            write!(html, "<th>{}</th>", header).unwrap();
+        }
+        html.push_str("</tr></thead>\n<tbody>\n");
+        for (rank, index) in order.iter().enumerate() {
+            let result = &self.results[*index];
+            let name = if rank < self.detailed {
+                format!("<a href=\"#result-{}\">{}</a>", rank, result.name)
+            } else {
+                result.name.to_string()
+            };
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{:.1}</td><td>{:.1}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                rank + 1,
+                name,
+                escape(&params_text(result)),
+                result.final_money,
+                total_return(result) * 100.,
+                max_drawdown(result.start_money, &result.money_evolution) * 100.,
+                result.win_ratio,
+                result.efficiency,
+                result.total_closed,
+                result.total_unclosed,
+                result.total_skipped,
+                escape(&result.rr_lisible)
+            )
+            .unwrap();
+        }
+        html.push_str("</tbody>\n</table>\n");
+    }
+}
+
+fn render_detail(html: &mut String, rank: usize, result: &StrategyResult) {
+    writeln!(
+        html,
+        "<h2 id=\"result-{}\">#{} {}</h2>",
+        rank,
+        rank + 1,
+        escape(&describe(result))
+    )
+    .unwrap();
+
+    if let Some(benchmark) = &result.benchmark {
+        html.push_str("<h3>Benchmark</h3>\n<table>\n");
+        for (label, value) in [
+            (
+                "Buy and hold return %",
+                benchmark.buy_and_hold_return * 100.,
+            ),
+            (
+                "Random entry return %",
+                benchmark.random_entry_return * 100.,
+            ),
+            ("Excess return %", benchmark.excess_return * 100.),
+            ("Excess over random %", benchmark.excess_over_random * 100.),
+            ("Alpha %", benchmark.alpha * 100.),
+            ("Beta", benchmark.beta),
+        ] {
+            writeln!(html, "<tr><th>{}</th><td>{:.2}</td></tr>", label, value).unwrap();
+        }
+        html.push_str("</table>\n");
+    }
+
+    // Avec le registre des trades chaque point de la courbe renvoie vers sa ligne du tableau
+    let points: Vec<(f64, Option<String>)> = match &result.ledger {
+        Some(ledger) => {
+            let mut money = result.start_money;
+            let mut points = vec![(money, None)];
+            for (k, record) in ledger.iter().enumerate() {
+                money += record.pnl;
+                points.push((money, Some(format!("trade-{}-{}", rank, k))));
+            }
+            points
+        }
+        None => std::iter::once(result.start_money)
+            .chain(result.money_evolution.iter().copied())
+            .map(|money| (money, None))
+            .collect(),
+    };
+    let equity: Vec<f64> = points.iter().map(|(money, _)| *money).collect();
+    html.push_str("<h3>Money</h3>\n");
+    line_chart(html, &points, "#1565c0");
+
+    let mut peak = f64::NEG_INFINITY;
+    let drawdown: Vec<(f64, Option<String>)> = equity
+        .iter()
+        .zip(points.iter())
+        .map(|(money, (_, anchor))| {
+            peak = peak.max(*money);
+            let drawdown = if peak > 0. {
+                -(peak - money) / peak * 100.
+            } else {
+                0.
+            };
+            (drawdown, anchor.clone())
+        })
+        .collect();
+    html.push_str("<h3>Drawdown %</h3>\n");
+    line_chart(html, &drawdown, "#c62828");
+
+    let Some(ledger) = &result.ledger else {
+        html.push_str("<p>Trade ledger not kept for this result.</p>\n");
+        return;
+    };
+    html.push_str("<h3>Return per trade distribution %</h3>\n");
+    let returns: Vec<f64> = ledger
+        .iter()
+        .map(|record| record.return_ratio() * 100.)
+        .collect();
+    histogram(html, &returns);
+
+    html.push_str("<h3>Trades</h3>\n<table class=\"sortable\">\n<thead><tr>");
+    for header in [
+        "#", "Open", "Close", "Entry", "SL", "TP", "Lots", "Money", "PnL", "Return %", "Result",
+    ] {
+        write!(html, "<th>{}</th>", header).unwrap();
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for (k, record) in ledger.iter().enumerate() {
+        let class = if record.pnl >= 0. { "win" } else { "lost" };
+        writeln!(
+            html,
+            "<tr id=\"trade-{}-{}\"><td>{}</td><td data-value=\"{}\">{}</td><td data-value=\"{}\">{}</td><td>{:.4}</td><td>{:.4}</td><td>{:.4}</td><td>{:.4}</td><td>{:.2}</td><td class=\"{}\">{:.2}</td><td>{:.2}</td><td>{:?}</td></tr>",
+            rank,
+            k,
+            k + 1,
+            record.open_time,
+            format_time(record.open_time),
+            record.close_time,
+            format_time(record.close_time),
+            record.entry_price,
+            record.sl,
+            record.tp,
+            record.lots,
+            record.money,
+            class,
+            record.pnl,
+            record.return_ratio() * 100.,
+            record.result
+        )
+        .unwrap();
+    }
+    html.push_str("</tbody>\n</table>\n");
+}
+
+fn line_chart(html: &mut String, points: &[(f64, Option<String>)], color: &str) {
+    let min = points.iter().map(|(y, _)| *y).fold(f64::INFINITY, f64::min);
+    let max = points
+        .iter()
+        .map(|(y, _)| *y)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let (min, max) = if max > min {
+        (min, max)
+    } else {
+        (min - 1., max + 1.)
+    };
+    let x = |i: usize| {
+        CHART_MARGIN
+            + i as f64 * (CHART_WIDTH - 2. * CHART_MARGIN) / (points.len().max(2) - 1) as f64
+    };
+    let y = |value: f64| {
+        CHART_HEIGHT
+            - CHART_MARGIN
+            - (value - min) / (max - min) * (CHART_HEIGHT - 2. * CHART_MARGIN)
+    };
+
+    writeln!(
+        html,
+        "<svg width=\"{}\" height=\"{}\" font-size=\"10\">",
+        CHART_WIDTH, CHART_HEIGHT
+    )
+    .unwrap();
+    for value in [min, max] {
+        writeln!(
+            html,
+            "<text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{:.2}</text><line x1=\"{}\" x2=\"{}\" y1=\"{:.1}\" y2=\"{:.1}\" stroke=\"#ccc\"/>",
+            CHART_MARGIN - 4.,
+            y(value) + 3.,
+            value,
+            CHART_MARGIN,
+            CHART_WIDTH - CHART_MARGIN,
+            y(value),
+            y(value)
+        )
+        .unwrap();
+    }
+    let path: Vec<String> = points
+        .iter()
+        .enumerate()
+        .map(|(i, (value, _))| format!("{:.1},{:.1}", x(i), y(*value)))
+        .collect();
+    writeln!(
+        html,
+        "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" points=\"{}\"/>",
+        color,
+        path.join(" ")
+    )
+    .unwrap();
+    for (i, (value, anchor)) in points.iter().enumerate() {
+        if let Some(anchor) = anchor {
+            writeln!(
+                html,
+                "<a href=\"#{}\"><circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\"><title>{} : {:.2}</title></circle></a>",
+                anchor,
+                x(i),
+                y(*value),
+                color,
+                anchor,
+                value
+            )
+            .unwrap();
+        }
+    }
+    html.push_str("</svg>\n");
+}
+
+fn histogram(html: &mut String, values: &[f64]) {
+    if values.is_empty() {
+        html.push_str("<p>No closed trade.</p>\n");
+        return;
+    }
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let width = if max > min {
+        (max - min) / HISTOGRAM_BINS as f64
+    } else {
+        1.
+    };
+    let mut bins = [0; HISTOGRAM_BINS];
+    for value in values {
+        bins[(((value - min) / width) as usize).min(HISTOGRAM_BINS - 1)] += 1;
+    }
+    let highest = *bins.iter().max().unwrap_or(&1) as f64;
+    let bar_width = (CHART_WIDTH - 2. * CHART_MARGIN) / HISTOGRAM_BINS as f64;
+
+    writeln!(
+        html,
+        "<svg width=\"{}\" height=\"{}\" font-size=\"10\">",
+        CHART_WIDTH, CHART_HEIGHT
+    )
+    .unwrap();
+    for (i, count) in bins.iter().enumerate() {
+        let from = min + i as f64 * width;
+        let height = *count as f64 / highest * (CHART_HEIGHT - 2. * CHART_MARGIN);
+        let color = if from + width / 2. >= 0. {
+            "#1a7f37"
+        } else {
+            "#c62828"
+        };
+        writeln!(
+            html,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{:.2} .. {:.2} : {}</title></rect>",
+            CHART_MARGIN + i as f64 * bar_width,
+            CHART_HEIGHT - CHART_MARGIN - height,
+            bar_width - 1.,
+            height,
+            color,
+            from,
+            from + width,
+            count
+        )
+        .unwrap();
+    }
+    writeln!(
+        html,
+        "<text x=\"{}\" y=\"{}\">{:.2}</text><text x=\"{}\" y=\"{}\" text-anchor=\"end\">{:.2}</text>",
+        CHART_MARGIN,
+        CHART_HEIGHT - CHART_MARGIN + 14.,
+        min,
+        CHART_WIDTH - CHART_MARGIN,
+        CHART_HEIGHT - CHART_MARGIN + 14.,
+        max
+    )
+    .unwrap();
+    html.push_str("</svg>\n");
+}
+
+fn params_text(result: &StrategyResult) -> String {
+    let mut params = vec![format!(
+        "tp={} sl={} risk={}",
+        result.strategy_params.tp_multiplier,
+        result.strategy_params.sl_multiplier,
+        result.strategy_params.risk_per_trade
+    )];
+    params.extend(
+        result
+            .patterns_params
+            .iter()
+            .map(|params| params.to_string()),
+    );
+    params.join(" ")
+}
+
+fn describe(result: &StrategyResult) -> String {
+    format!("{} ({})", result.name, params_text(result))
+}
+
+fn format_time(timestamp: i64) -> String {
+    Utc.timestamp_millis_opt(timestamp)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
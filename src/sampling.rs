@@ -0,0 +1,148 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::*;
+use crate::param_space::*;
+use crate::strategies::*;
+use crate::strategies_creator::*;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SamplingMethod {
+    Uniform,
+    // Uniforme sur le logarithme des bornes, pour les paramètres qui couvrent plusieurs ordres de grandeur
+    LogUniform,
+    // Une valeur par strate de chaque dimension, les strates étant mélangées entre dimensions
+    LatinHypercube,
+}
+
+// Tire `n` points dans [0, 1)^dimensions
+pub fn unit_samples(
+    n: usize,
+    dimensions: usize,
+    method: SamplingMethod,
+    rng: &mut StdRng,
+) -> Vec<Vec<f64>> {
+    match method {
+        SamplingMethod::Uniform | SamplingMethod::LogUniform => (0..n)
+            .map(|_| (0..dimensions).map(|_| rng.gen::<f64>()).collect())
+            .collect(),
+        SamplingMethod::LatinHypercube => {
+            let mut samples = vec![vec![0.; dimensions]; n];
+            for d in 0..dimensions {
+                let mut strata: Vec<usize> = (0..n).collect();
+                strata.shuffle(rng);
+                for (sample, stratum) in samples.iter_mut().zip(strata) {
+                    sample[d] = (stratum as f64 + rng.gen::<f64>()) / n as f64;
+                }
+            }
+            samples
+        }
+    }
+}
+
+// Passe de [0, 1) à [min, max]. L'échelle log n'est utilisée que si les bornes sont positives.
+pub fn scale_unit(unit: f64, min: f64, max: f64, method: SamplingMethod) -> f64 {
+    if max <= min {
+        return min;
+    }
+    if method == SamplingMethod::LogUniform && min > 0. {
+        (min.ln() + unit * (max.ln() - min.ln())).exp()
+    } else {
+        min + unit * (max - min)
+    }
+}
+
+// Plages tirées pour une stratégie à patterns. pattern_size et pattern_range correspondent
+// aux dimensions de PatternStrategyBuilder::dimension_names.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PatternRanges {
+    pub tp: ParamMultiplier<f64>,
+    pub sl: ParamMultiplier<f64>,
+    pub pattern_size: ParamMultiplier<usize>,
+    pub pattern_range: ParamMultiplier<usize>,
+    pub risk: ParamMultiplier<f64>,
+}
+
+fn sample_strategies(
+    name: StrategyName,
+    n: usize,
+    seed: u64,
+    method: SamplingMethod,
+    start_money: f64,
+    ranges: PatternRanges,
+    market_type: MarketType,
+) -> Vec<Strategy> {
+    let space = PatternStrategyBuilder::space(
+        name,
+        ranges.tp,
+        ranges.sl,
+        ranges.pattern_size,
+        ranges.pattern_range,
+        ranges.risk,
+    );
+    let builder = PatternStrategyBuilder {
+        name,
+        start_money,
+        market_type,
+    };
+    space.instantiate(&space.sample(n, seed, method), &builder)
+}
+
+pub fn sample_w_pattern_strategies(
+    n: usize,
+    seed: u64,
+    method: SamplingMethod,
+    start_money: f64,
+    ranges: PatternRanges,
+    market_type: MarketType,
+) -> Vec<Strategy> {
+    sample_strategies(
+        StrategyName::W,
+        n,
+        seed,
+        method,
+        start_money,
+        ranges,
+        market_type,
+    )
+}
+
+pub fn sample_m_pattern_strategies(
+    n: usize,
+    seed: u64,
+    method: SamplingMethod,
+    start_money: f64,
+    ranges: PatternRanges,
+    market_type: MarketType,
+) -> Vec<Strategy> {
+    sample_strategies(
+        StrategyName::M,
+        n,
+        seed,
+        method,
+        start_money,
+        ranges,
+        market_type,
+    )
+}
+
+pub fn sample_reversal_pattern_strategies(
+    n: usize,
+    seed: u64,
+    method: SamplingMethod,
+    start_money: f64,
+    ranges: PatternRanges,
+    market_type: MarketType,
+) -> Vec<Strategy> {
+    sample_strategies(
+        StrategyName::BullReversal,
+        n,
+        seed,
+        method,
+        start_money,
+        ranges,
+        market_type,
+    )
+}
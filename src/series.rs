@@ -0,0 +1,162 @@
+use std::ops::Range;
+
+use crate::patterns::MathKLine;
+
+// Klines stockées colonne par colonne : les détecteurs de patterns parcourent des
+// tableaux contigus de f64 au lieu de cloner des MathKLine
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PriceSeries {
+    pub open_time: Vec<i64>,
+    pub open: Vec<f64>,
+    pub high: Vec<f64>,
+    pub low: Vec<f64>,
+    pub close: Vec<f64>,
+    pub volume: Vec<f64>,
+    pub close_time: Vec<i64>,
+}
+
+impl PriceSeries {
+    pub fn new() -> Self {
+        PriceSeries::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        PriceSeries {
+            open_time: Vec::with_capacity(capacity),
+            open: Vec::with_capacity(capacity),
+            high: Vec::with_capacity(capacity),
+            low: Vec::with_capacity(capacity),
+            close: Vec::with_capacity(capacity),
+            volume: Vec::with_capacity(capacity),
+            close_time: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn from_klines(klines: &[MathKLine]) -> Self {
+        let mut series = PriceSeries::with_capacity(klines.len());
+        for kline in klines {
+            series.push(kline);
+        }
+        series
+    }
+
+    pub fn push(&mut self, kline: &MathKLine) {
+        self.open_time.push(kline.open_time);
+        self.open.push(kline.open);
+        self.high.push(kline.high);
+        self.low.push(kline.low);
+        self.close.push(kline.close);
+        self.volume.push(kline.volume.parse().unwrap_or(0.));
+        self.close_time.push(kline.close_time);
+    }
+
+    // Ajoute la kline index d'une autre série
+    pub fn push_from(&mut self, series: SeriesView, index: usize) {
+        self.open_time.push(series.open_time[index]);
+        self.open.push(series.open[index]);
+        self.high.push(series.high[index]);
+        self.low.push(series.low[index]);
+        self.close.push(series.close[index]);
+        self.volume.push(series.volume[index]);
+        self.close_time.push(series.close_time[index]);
+    }
+
+    pub fn len(&self) -> usize {
+        self.close.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.close.is_empty()
+    }
+
+    pub fn view(&self) -> SeriesView<'_> {
+        SeriesView {
+            open_time: &self.open_time,
+            open: &self.open,
+            high: &self.high,
+            low: &self.low,
+            close: &self.close,
+            volume: &self.volume,
+            close_time: &self.close_time,
+        }
+    }
+}
+
+// Klines complètes indexées comme la série. Elles ne sont construites qu'à la demande,
+// pour les klines gardées sur les trades
+pub trait KlineSource: Send + Sync {
+    fn kline(&self, index: usize) -> MathKLine;
+}
+
+impl KlineSource for Vec<MathKLine> {
+    fn kline(&self, index: usize) -> MathKLine {
+        self[index].clone()
+    }
+}
+
+// Fenêtre sur une PriceSeries, sans copie
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SeriesView<'a> {
+    pub open_time: &'a [i64],
+    pub open: &'a [f64],
+    pub high: &'a [f64],
+    pub low: &'a [f64],
+    pub close: &'a [f64],
+    pub volume: &'a [f64],
+    pub close_time: &'a [i64],
+}
+
+impl<'a> SeriesView<'a> {
+    pub fn len(&self) -> usize {
+        self.close.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.close.is_empty()
+    }
+
+    // Comme slice::get : None si l'intervalle sort de la fenêtre
+    pub fn get(&self, range: Range<usize>) -> Option<SeriesView<'a>> {
+        if range.start > range.end || range.end > self.len() {
+            return None;
+        }
+        Some(SeriesView {
+            open_time: &self.open_time[range.clone()],
+            open: &self.open[range.clone()],
+            high: &self.high[range.clone()],
+            low: &self.low[range.clone()],
+            close: &self.close[range.clone()],
+            volume: &self.volume[range.clone()],
+            close_time: &self.close_time[range],
+        })
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> SeriesView<'a> {
+        match self.get(range.clone()) {
+            Some(view) => view,
+            None => panic!(
+                "range {:?} out of bounds for a series of {} klines",
+                range,
+                self.len()
+            ),
+        }
+    }
+
+    pub fn from(&self, start: usize) -> SeriesView<'a> {
+        self.slice(start..self.len())
+    }
+
+    pub fn is_up(&self, index: usize) -> bool {
+        self.close[index] > self.open[index]
+    }
+
+    pub fn is_down(&self, index: usize) -> bool {
+        self.close[index] < self.open[index]
+    }
+
+    // Index de la première kline clôturée à partir de time (len() si aucune)
+    pub fn first_closing_at(&self, time: i64) -> usize {
+        self.close_time
+            .partition_point(|close_time| *close_time < time)
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::*;
+use crate::error::Result;
+use crate::metrics::*;
+
+const STRATEGY_PARAMS: [&str; 3] = ["tp_multiplier", "sl_multiplier", "risk_per_trade"];
+const CELL_SIZE: usize = 40;
+const MARGIN: usize = 80;
+
+// Valeur numérique d'un paramètre de stratégie ou de pattern
+pub fn param_value(result: &StrategyResult, name: &str) -> Option<f64> {
+    match name {
+        "tp_multiplier" => Some(result.strategy_params.tp_multiplier),
+        "sl_multiplier" => Some(result.strategy_params.sl_multiplier),
+        "risk_per_trade" => Some(result.strategy_params.risk_per_trade),
+        _ => result.pattern_param(name),
+    }
+}
+
+// Noms des paramètres numériques présents dans un résultat
+pub fn param_names(result: &StrategyResult) -> Vec<String> {
+    let mut names: BTreeSet<String> = STRATEGY_PARAMS
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    for params in result.patterns_params.iter() {
+        for (name, _) in params.values() {
+            names.insert(name.to_string());
+        }
+    }
+    names.into_iter().collect()
+}
+
+fn distinct_values(results: &[StrategyResult], name: &str) -> Vec<f64> {
+    let mut values: Vec<f64> = results
+        .iter()
+        .filter_map(|result| param_value(result, name))
+        .collect();
+    values.sort_by(|a, b| a.total_cmp(b));
+    values.dedup();
+    values
+}
+
+fn index_of(values: &[f64], value: f64) -> Option<usize> {
+    values
+        .binary_search_by(|other| other.total_cmp(&value))
+        .ok()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Aggregation {
+    // Moyenne des résultats qui tombent dans la même case (les autres paramètres variant)
+    Mean,
+    // Meilleur résultat de la case selon la métrique
+    Best,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StabilityGrid {
+    pub x_param: String,
+    pub y_param: String,
+    pub metric: Metric,
+    pub x_values: Vec<f64>,
+    pub y_values: Vec<f64>,
+    // cells[y][x], None si aucun résultat ne correspond à la case
+    pub cells: Vec<Vec<Option<f64>>>,
+}
+
+impl StabilityGrid {
+    pub fn new(
+        results: &[StrategyResult],
+        x_param: &str,
+        y_param: &str,
+        metric: Metric,
+        aggregation: Aggregation,
+    ) -> Self {
+        let x_values = distinct_values(results, x_param);
+        let y_values = distinct_values(results, y_param);
+        let mut buckets: Vec<Vec<Vec<f64>>> =
+            vec![vec![Vec::new(); x_values.len()]; y_values.len()];
+        for result in results {
+            if let (Some(x), Some(y)) = (param_value(result, x_param), param_value(result, y_param))
+            {
+                if let (Some(x), Some(y)) = (index_of(&x_values, x), index_of(&y_values, y)) {
+                    buckets[y][x].push(metric.value(result));
+                }
+            }
+        }
+
+        let cells = buckets
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|values| {
+                        if values.is_empty() {
+                            return None;
+                        }
+                        match aggregation {
+                            Aggregation::Mean => {
+                                Some(values.iter().sum::<f64>() / values.len() as f64)
+                            }
+                            Aggregation::Best if metric.higher_is_better() => {
+                                values.into_iter().reduce(f64::max)
+                            }
+                            Aggregation::Best => values.into_iter().reduce(f64::min),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        StabilityGrid {
+            x_param: x_param.to_string(),
+            y_param: y_param.to_string(),
+            metric,
+            x_values,
+            y_values,
+            cells,
+        }
+    }
+
+    fn bounds(&self) -> Option<(f64, f64)> {
+        let values = self
+            .cells
+            .iter()
+            .flatten()
+            .flatten()
+            .filter(|v| v.is_finite());
+        let min = values.clone().copied().reduce(f64::min)?;
+        let max = values.copied().reduce(f64::max)?;
+        Some((min, max))
+    }
+
+    // Première ligne : y_param\x_param puis les valeurs de x, une ligne par valeur de y
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\\{}", self.y_param, self.x_param);
+        for x in self.x_values.iter() {
+            write!(csv, ",{}", x).unwrap();
+        }
+        csv.push('\n');
+        for (y, row) in self.y_values.iter().zip(self.cells.iter()) {
+            write!(csv, "{}", y).unwrap();
+            for cell in row {
+                match cell {
+                    Some(value) => write!(csv, ",{}", value).unwrap(),
+                    None => csv.push(','),
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn to_svg(&self) -> String {
+        let width = MARGIN * 2 + CELL_SIZE * self.x_values.len();
+        let height = MARGIN * 2 + CELL_SIZE * self.y_values.len();
+        let (min, max) = self.bounds().unwrap_or((0., 0.));
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"10\">\n",
+            width, height
+        );
+        writeln!(
+            svg,
+            "<text x=\"{}\" y=\"20\" text-anchor=\"middle\" font-size=\"14\">{} ({} x {})</text>",
+            width / 2,
+            self.metric,
+            self.x_param,
+            self.y_param
+        )
+        .unwrap();
+
+        // La plus petite valeur de y est en bas
+        for (row_index, row) in self.cells.iter().enumerate() {
+            let top = MARGIN + CELL_SIZE * (self.y_values.len() - 1 - row_index);
+            for (column, cell) in row.iter().enumerate() {
+                let left = MARGIN + CELL_SIZE * column;
+                let (color, title) = match cell {
+                    Some(value) => (
+                        heat_color(*value, min, max, self.metric),
+                        format!("{}", value),
+                    ),
+                    None => (String::from("#dddddd"), String::from("-")),
+                };
+                writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>{}={} {}={} : {}</title></rect>",
+                    left,
+                    top,
+                    CELL_SIZE,
+                    CELL_SIZE,
+                    color,
+                    self.x_param,
+                    self.x_values[column],
+                    self.y_param,
+                    self.y_values[row_index],
+                    title
+                )
+                .unwrap();
+            }
+            writeln!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+                MARGIN - 4,
+                top + CELL_SIZE / 2 + 3,
+                self.y_values[row_index]
+            )
+            .unwrap();
+        }
+        for (column, x) in self.x_values.iter().enumerate() {
+            writeln!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                MARGIN + CELL_SIZE * column + CELL_SIZE / 2,
+                height - MARGIN + 14,
+                x
+            )
+            .unwrap();
+        }
+        writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            width / 2,
+            height - MARGIN / 2 + 10,
+            self.x_param
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<text x=\"14\" y=\"{}\" text-anchor=\"middle\" transform=\"rotate(-90 14 {})\">{}</text>",
+            height / 2,
+            height / 2,
+            self.y_param
+        )
+        .unwrap();
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn write_csv(&self, path: &str) -> Result<()> {
+        Ok(File::create(path)?.write_all(self.to_csv().as_bytes())?)
+    }
+
+    pub fn write_svg(&self, path: &str) -> Result<()> {
+        Ok(File::create(path)?.write_all(self.to_svg().as_bytes())?)
+    }
+}
+
+// Du rouge (mauvais) au vert (bon) en passant par le jaune
+fn heat_color(value: f64, min: f64, max: f64, metric: Metric) -> String {
+    let mut ratio = if max > min && value.is_finite() {
+        (value - min) / (max - min)
+    } else {
+        0.5
+    };
+    if !metric.higher_is_better() {
+        ratio = 1. - ratio;
+    }
+    let red = (255. * (2. - 2. * ratio).min(1.)) as u8;
+    let green = (255. * (2. * ratio).min(1.)) as u8;
+    format!("#{:02x}{:02x}40", red, green)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobustnessScore {
+    // Position du résultat dans le vecteur d'entrée
+    pub index: usize,
+    pub value: f64,
+    pub neighbours: usize,
+    // Moyenne de la métrique sur le résultat et ses voisins
+    pub neighbourhood_mean: f64,
+    // Pire valeur du voisinage, résultat compris
+    pub neighbourhood_worst: f64,
+}
+
+// Les voisins d'un résultat sont les résultats du même type de stratégie dont chaque paramètre
+// est au plus à `radius` crans sur la grille des valeurs testées.
+pub fn robustness_scores(
+    results: &[StrategyResult],
+    metric: Metric,
+    radius: usize,
+) -> Vec<RobustnessScore> {
+    let mut names: Vec<String> = results.iter().flat_map(param_names).collect();
+    names.sort();
+    names.dedup();
+    let grids: Vec<Vec<f64>> = names
+        .iter()
+        .map(|name| distinct_values(results, name))
+        .collect();
+    let coordinates: Vec<Vec<Option<usize>>> = results
+        .iter()
+        .map(|result| {
+            names
+                .iter()
+                .zip(grids.iter())
+                .map(|(name, grid)| index_of(grid, param_value(result, name)?))
+                .collect()
+        })
+        .collect();
+    let values: Vec<f64> = results.iter().map(|result| metric.value(result)).collect();
+
+    (0..results.len())
+        .map(|i| {
+            let neighbourhood: Vec<usize> = (0..results.len())
+                .filter(|j| {
+                    results[*j].name == results[i].name
+                        && coordinates[i]
+                            .iter()
+                            .zip(coordinates[*j].iter())
+                            .all(|(a, b)| match (a, b) {
+                                (Some(a), Some(b)) => a.abs_diff(*b) <= radius,
+                                (None, None) => true,
+                                _ => false,
+                            })
+                })
+                .collect();
+            let mean =
+                neighbourhood.iter().map(|j| values[*j]).sum::<f64>() / neighbourhood.len() as f64;
+            let worst = neighbourhood
+                .iter()
+                .map(|j| values[*j])
+                .reduce(|a, b| {
+                    if metric.higher_is_better() {
+                        a.min(b)
+                    } else {
+                        a.max(b)
+                    }
+                })
+                .unwrap_or(values[i]);
+            RobustnessScore {
+                index: i,
+                value: values[i],
+                neighbours: neighbourhood.len() - 1,
+                neighbourhood_mean: mean,
+                neighbourhood_worst: worst,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,232 @@
+use std::sync::Arc;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::account::*;
+use crate::backtest::*;
+use crate::benchmark::*;
+use crate::error::Result;
+use crate::filters::*;
+use crate::metrics::*;
+use crate::series::*;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS results (
+    dataset_hash TEXT NOT NULL,
+    params_hash TEXT NOT NULL,
+    name TEXT NOT NULL,
+    strategy_params TEXT NOT NULL,
+    patterns_params TEXT NOT NULL,
+    start_money REAL NOT NULL,
+    final_money REAL NOT NULL,
+    total_return REAL NOT NULL,
+    max_drawdown REAL NOT NULL,
+    win_ratio REAL NOT NULL,
+    efficiency REAL NOT NULL,
+    total_closed INTEGER NOT NULL,
+    total_unclosed INTEGER NOT NULL,
+    total_skipped INTEGER NOT NULL,
+    result TEXT NOT NULL,
+    PRIMARY KEY (dataset_hash, params_hash)
+);
+CREATE TABLE IF NOT EXISTS trades (
+    dataset_hash TEXT NOT NULL,
+    params_hash TEXT NOT NULL,
+    open_time INTEGER NOT NULL,
+    close_time INTEGER NOT NULL,
+    entry_price REAL NOT NULL,
+    sl REAL NOT NULL,
+    tp REAL NOT NULL,
+    lots REAL NOT NULL,
+    money REAL NOT NULL,
+    pnl REAL NOT NULL,
+    result TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS trades_result ON trades (dataset_hash, params_hash);
+";
+
+// FNV-1a 64 bits, stable d'une exécution à l'autre contrairement au hasher de std
+#[derive(Clone, Copy)]
+pub struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(FNV_OFFSET)
+    }
+}
+
+impl Fnv {
+    pub fn write(&mut self, bytes: &[u8]) -> &mut Self {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+        self
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+pub fn dataset_hash(series: SeriesView) -> String {
+    let mut hasher = Fnv::default();
+    for i in 0..series.len() {
+        hasher
+            .write(&series.open_time[i].to_le_bytes())
+            .write(&series.close_time[i].to_le_bytes())
+            .write(&series.open[i].to_bits().to_le_bytes())
+            .write(&series.high[i].to_bits().to_le_bytes())
+            .write(&series.low[i].to_bits().to_le_bytes())
+            .write(&series.close[i].to_bits().to_le_bytes());
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+// Le format Debug des f64 est exact, deux stratégies ont donc le même hash uniquement si
+// leurs paramètres, leurs filtres, les limites du portefeuille, les coûts et le benchmark
+// sont identiques
+pub fn params_hash(
+    strategy: &Strategy,
+    filters: &[Arc<dyn SignalFilter>],
+    limits: &PortfolioLimits,
+    costs: &CostModel,
+    only_potential: bool,
+    benchmark: &Option<BenchmarkConfig>,
+) -> String {
+    let mut hasher = Fnv::default();
+    hasher.write(format!("{:?}", strategy.1).as_bytes());
+    for params in strategy.2.iter() {
+        hasher.write(format!("{:?}", params.to_config()).as_bytes());
+    }
+    for filter in filters {
+        hasher.write(filter.describe().as_bytes());
+    }
+    hasher
+        .write(format!("{:?}", limits).as_bytes())
+        .write(format!("{:?}", costs).as_bytes())
+        .write(format!("{:?}", benchmark).as_bytes())
+        .write(&[only_potential as u8]);
+    format!("{:016x}", hasher.finish())
+}
+
+pub struct ResultStore {
+    connection: Connection,
+}
+
+impl ResultStore {
+    pub fn open(path: &str) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(ResultStore { connection })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn contains(&self, dataset_hash: &str, params_hash: &str) -> Result<bool> {
+        let found = self
+            .connection
+            .query_row(
+                "SELECT 1 FROM results WHERE dataset_hash = ?1 AND params_hash = ?2",
+                params![dataset_hash, params_hash],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        Ok(found)
+    }
+
+    pub fn insert(
+        &mut self,
+        dataset_hash: &str,
+        params_hash: &str,
+        result: &StrategyResult,
+    ) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO results VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                dataset_hash,
+                params_hash,
+                result.name.to_string(),
+                serde_json::to_string(&result.strategy_params)?,
+                serde_json::to_string(&result.patterns_params)?,
+                result.start_money,
+                result.final_money,
+                total_return(result),
+                max_drawdown(result.start_money, &result.money_evolution),
+                result.win_ratio as f64,
+                result.efficiency as f64,
+                result.total_closed as i64,
+                result.total_unclosed as i64,
+                result.total_skipped as i64,
+                serde_json::to_string(result)?,
+            ],
+        )?;
+        transaction.execute(
+            "DELETE FROM trades WHERE dataset_hash = ?1 AND params_hash = ?2",
+            params![dataset_hash, params_hash],
+        )?;
+        if let Some(ledger) = &result.ledger {
+            let mut statement = transaction.prepare(
+                "INSERT INTO trades VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            for record in ledger {
+                statement.execute(params![
+                    dataset_hash,
+                    params_hash,
+                    record.open_time,
+                    record.close_time,
+                    record.entry_price,
+                    record.sl,
+                    record.tp,
+                    record.lots,
+                    record.money,
+                    record.pnl,
+                    format!("{:?}", record.result),
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn get(&self, dataset_hash: &str, params_hash: &str) -> Result<Option<StrategyResult>> {
+        let json = self
+            .connection
+            .query_row(
+                "SELECT result FROM results WHERE dataset_hash = ?1 AND params_hash = ?2",
+                params![dataset_hash, params_hash],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        // Un résultat dans un ancien format est recalculé
+        Ok(json.and_then(|json| serde_json::from_str(&json).ok()))
+    }
+
+    pub fn results_for_dataset(&self, dataset_hash: &str) -> Result<Vec<StrategyResult>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT result FROM results WHERE dataset_hash = ?1")?;
+        let rows = statement.query_map(params![dataset_hash], |row| row.get::<_, String>(0))?;
+        let mut results = Vec::new();
+        for json in rows {
+            if let Ok(result) = serde_json::from_str(&json?) {
+                results.push(result);
+            }
+        }
+        Ok(results)
+    }
+}
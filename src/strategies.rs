@@ -1,13 +1,16 @@
-use std::sync::mpsc::Sender;
 use std::sync::Arc;
 
 use serde::Deserialize;
 use serde::Serialize;
 
 use crate::backtest::*;
+use crate::confluence::*;
+use crate::error::{Error, Result};
 use crate::patterns::*;
+use crate::progress::*;
+use crate::series::*;
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum MarketType {
     Spot,
     Futures,
@@ -18,161 +21,313 @@ pub struct StrategyParams {
     pub tp_multiplier: f64,
     pub sl_multiplier: f64,
     pub risk_per_trade: f64,
-    #[serde(skip_serializing)]
+    #[serde(default)]
     pub money: f64,
     pub name: StrategyName,
     pub market_type: MarketType,
 }
 
-pub type StrategyFunc = fn(
-    &Vec<MathKLine>,
-    Option<&Sender<f32>>,
+// Entrée trouvée sur la série : la MathKLine du signal n'est construite que pour les
+// trades gardés
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Signal {
+    pub open_time: i64,
+    pub entry_price: f64,
+    pub sl: f64,
+    pub tp: f64,
+}
+
+impl Signal {
+    pub fn is_long(&self) -> bool {
+        self.sl < self.tp
+    }
+
+    pub fn to_trade(self, opening_kline: MathKLine, strategy: StrategyName) -> Trade {
+        Trade {
+            entry_price: self.entry_price,
+            sl: self.sl,
+            tp: self.tp,
+            open_time: self.open_time,
+            opening_kline,
+            money: 0.,
+            benefits: 0.,
+            loss: 0.,
+            taxes: 0.,
+            lots: 0.,
+            close_time: 0,
+            closing_kline: None,
+            status: Status::NotOpened,
+            strategy,
+        }
+    }
+}
+
+impl From<&Trade> for Signal {
+    fn from(trade: &Trade) -> Self {
+        Signal {
+            open_time: trade.open_time,
+            entry_price: trade.entry_price,
+            sl: trade.sl,
+            tp: trade.tp,
+        }
+    }
+}
+
+// Le signal et l'index de sa kline dans la série
+pub type TradeFinder = fn(
+    SeriesView,
     StrategyParams,
-    Arc<Vec<Arc<dyn PatternParams>>>,
-) -> Vec<Trade>;
+    &Arc<Vec<Arc<dyn PatternParams>>>,
+    bool,
+) -> Option<(usize, Signal)>;
 
-pub fn create_wpattern_trades(
-    chunk: &Vec<MathKLine>,
-    progression_tracker: Option<&Sender<f32>>,
+pub fn trade_finder(name: StrategyName) -> Option<TradeFinder> {
+    match name {
+        StrategyName::W => Some(find_wpattern_trade),
+        StrategyName::M => Some(find_mpattern_trade),
+        StrategyName::BullReversal => Some(find_bull_reversal_trade),
+        StrategyName::Confluence | StrategyName::None => None,
+    }
+}
+
+pub fn strategy_func_for(name: StrategyName) -> Option<StrategyFunc> {
+    match name {
+        StrategyName::W => Some(create_wpattern_trades),
+        StrategyName::M => Some(create_mpattern_trades),
+        StrategyName::BullReversal => Some(create_bull_reversal_trades),
+        StrategyName::Confluence => Some(create_confluence_trades),
+        StrategyName::None => None,
+    }
+}
+
+// Vérifie que les paramètres peuvent être backtestés et que le pattern correspond à la stratégie
+pub fn validate_strategy(
+    strategy_params: &StrategyParams,
+    patterns_params: &[Arc<dyn PatternParams>],
+) -> Result<()> {
+    for (name, value) in [
+        ("tp_multiplier", strategy_params.tp_multiplier),
+        ("sl_multiplier", strategy_params.sl_multiplier),
+        ("money", strategy_params.money),
+    ] {
+        if !value.is_finite() || value <= 0. {
+            return Err(Error::InvalidParams(format!(
+                "{} must be positive, got {}",
+                name, value
+            )));
+        }
+    }
+    if !(strategy_params.risk_per_trade > 0. && strategy_params.risk_per_trade <= 1.) {
+        return Err(Error::InvalidParams(format!(
+            "risk_per_trade must be in ]0, 1], got {}",
+            strategy_params.risk_per_trade
+        )));
+    }
+
+    let params = patterns_params.first().ok_or_else(|| {
+        Error::InvalidParams(format!(
+            "no pattern params for strategy {}",
+            strategy_params.name
+        ))
+    })?;
+    let config = params.to_config();
+    let expected = match strategy_params.name {
+        StrategyName::W => PatternName::W,
+        StrategyName::M => PatternName::M,
+        StrategyName::BullReversal => PatternName::BullReversal,
+        StrategyName::Confluence => PatternName::Confluence,
+        StrategyName::None => {
+            return Err(Error::InvalidParams(String::from(
+                "strategy None cannot be run",
+            )))
+        }
+    };
+    if config.name() != expected {
+        return Err(Error::InvalidParams(format!(
+            "strategy {} cannot use {} pattern params",
+            strategy_params.name,
+            config.name()
+        )));
+    }
+    if let Some((name, _)) = config.values().into_iter().find(|(_, value)| *value < 1.) {
+        return Err(Error::InvalidParams(format!("{} must be at least 1", name)));
+    }
+    if strategy_params.name == StrategyName::Confluence {
+        validate_confluence(patterns_params)?;
+    }
+    Ok(())
+}
+
+fn create_trades(
+    klines: &dyn KlineSource,
+    series: SeriesView,
+    progression_tracker: Option<&ProgressTracker>,
     strategy_params: StrategyParams,
     patterns_params: Arc<Vec<Arc<dyn PatternParams>>>,
     potential_only: bool,
-) -> Vec<Trade> {
+    finder: TradeFinder,
+) -> Result<Vec<Trade>> {
+    validate_strategy(&strategy_params, &patterns_params)?;
+    Ok(find_signals(
+        series,
+        progression_tracker,
+        strategy_params,
+        &patterns_params,
+        potential_only,
+        finder,
+    )?
+    .into_iter()
+    .map(|(index, signal)| signal.to_trade(klines.kline(index), strategy_params.name))
+    .collect())
+}
+
+// Signaux trouvés par finder sur toute la fenêtre, avec l'index de leur kline
+pub fn find_signals(
+    series: SeriesView,
+    progression_tracker: Option<&ProgressTracker>,
+    strategy_params: StrategyParams,
+    patterns_params: &Arc<Vec<Arc<dyn PatternParams>>>,
+    potential_only: bool,
+    finder: TradeFinder,
+) -> Result<Vec<(usize, Signal)>> {
     let mut result_vec = Vec::new();
     let mut j = 0;
     let mut last_sent = 0;
-    while j < chunk.len() {
-        if let Some(wpattern_params) = patterns_params[0].downcast_ref::<WPatternParams>() {
-            if let Some(result) = find_w_pattern(&chunk[j..], *wpattern_params, potential_only) {
-                j += result.end_index;
-                result_vec.push(Trade {
-                    entry_price: result.neckline_price,
-                    sl: result.lower_price
-                        - ((result.neckline_price - result.lower_price)
-                            * (strategy_params.sl_multiplier - 1.)),
-                    tp: result.neckline_price
-                        + ((result.neckline_price - result.lower_price)
-                            * strategy_params.tp_multiplier),
-                    open_time: result.end_time,
-                    opening_kline: chunk[j].clone(),
-                    money: 0.,
-                    benefits: 0.,
-                    loss: 0.,
-                    taxes: 0.,
-                    lots: 0.,
-                    close_time: 0,
-                    closing_kline: None,
-                    status: Status::NotOpened,
-                    strategy: strategy_params.name,
-                });
-            } else {
-                j += 1;
-            }
-            if last_sent + 1000 < j && progression_tracker.is_some() {
-                progression_tracker
-                    .unwrap()
-                    .send(j as f32 / chunk.len() as f32 * 50.);
-                last_sent = j;
+    while j < series.len() {
+        if let Some((end_index, signal)) = finder(
+            series.from(j),
+            strategy_params,
+            patterns_params,
+            potential_only,
+        ) {
+            j += end_index;
+            result_vec.push((j, signal));
+        } else {
+            j += 1;
+        }
+        if last_sent + 1000 < j {
+            if let Some(tracker) = progression_tracker {
+                tracker.check_cancelled()?;
+                tracker.report(j as f32 / series.len() as f32 * 0.5);
             }
+            last_sent = j;
         }
     }
-    result_vec
+    Ok(result_vec)
+}
+
+pub fn find_wpattern_trade(
+    series: SeriesView,
+    strategy_params: StrategyParams,
+    patterns_params: &Arc<Vec<Arc<dyn PatternParams>>>,
+    potential_only: bool,
+) -> Option<(usize, Signal)> {
+    let wpattern_params = patterns_params.first()?.downcast_ref::<WPatternParams>()?;
+    let result = find_w_pattern(series, *wpattern_params, potential_only)?;
+    let signal = Signal {
+        open_time: result.end_time,
+        entry_price: result.neckline_price,
+        sl: result.lower_price
+            - ((result.neckline_price - result.lower_price) * (strategy_params.sl_multiplier - 1.)),
+        tp: result.neckline_price
+            + ((result.neckline_price - result.lower_price) * strategy_params.tp_multiplier),
+    };
+    Some((result.end_index, signal))
+}
+
+pub fn find_mpattern_trade(
+    series: SeriesView,
+    strategy_params: StrategyParams,
+    patterns_params: &Arc<Vec<Arc<dyn PatternParams>>>,
+    potential_only: bool,
+) -> Option<(usize, Signal)> {
+    let mpattern_params = patterns_params.first()?.downcast_ref::<MPatternParams>()?;
+    let result = find_m_pattern(series, *mpattern_params, potential_only)?;
+    let signal = Signal {
+        open_time: result.end_time,
+        entry_price: result.neckline_price,
+        sl: result.higher_price
+            - ((result.neckline_price - result.higher_price)
+                * (strategy_params.sl_multiplier - 1.)),
+        tp: result.neckline_price
+            + ((result.neckline_price - result.higher_price) * strategy_params.tp_multiplier),
+    };
+    Some((result.end_index, signal))
+}
+
+pub fn find_bull_reversal_trade(
+    series: SeriesView,
+    strategy_params: StrategyParams,
+    patterns_params: &Arc<Vec<Arc<dyn PatternParams>>>,
+    potential_only: bool,
+) -> Option<(usize, Signal)> {
+    let reversal_pattern_params = patterns_params
+        .first()?
+        .downcast_ref::<ReversalPatternParams>()?;
+    let result = find_bull_reversal(series, *reversal_pattern_params, potential_only)?;
+    let signal = Signal {
+        open_time: result.end_time,
+        entry_price: result.end_price,
+        sl: result.peak_price * strategy_params.sl_multiplier,
+        tp: result.end_price
+            + ((result.end_price - result.peak_price) * strategy_params.tp_multiplier),
+    };
+    Some((result.end_index, signal))
+}
+
+pub fn create_wpattern_trades(
+    klines: &dyn KlineSource,
+    series: SeriesView,
+    progression_tracker: Option<&ProgressTracker>,
+    strategy_params: StrategyParams,
+    patterns_params: Arc<Vec<Arc<dyn PatternParams>>>,
+    potential_only: bool,
+) -> Result<Vec<Trade>> {
+    create_trades(
+        klines,
+        series,
+        progression_tracker,
+        strategy_params,
+        patterns_params,
+        potential_only,
+        find_wpattern_trade,
+    )
 }
 
 pub fn create_mpattern_trades(
-    chunk: &Vec<MathKLine>,
-    progression_tracker: Option<&Sender<f32>>,
+    klines: &dyn KlineSource,
+    series: SeriesView,
+    progression_tracker: Option<&ProgressTracker>,
     strategy_params: StrategyParams,
     patterns_params: Arc<Vec<Arc<dyn PatternParams>>>,
     potential_only: bool,
-) -> Vec<Trade> {
-    let mut result_vec = Vec::new();
-    let mut j = 0;
-    let mut last_sent = 0;
-    while j < chunk.len() {
-        if let Some(mpattern_params) = patterns_params[0].downcast_ref::<MPatternParams>() {
-            if let Some(result) = find_m_pattern(&chunk[j..], *mpattern_params, potential_only) {
-                    j += result.end_index;
-                    //println!("trade {} open time == {}, result = {:#?}", j, result.end_time, result);
-                    result_vec.push(Trade {
-                    entry_price: result.neckline_price,
-                    sl: result.higher_price
-                        - ((result.neckline_price - result.higher_price)
-                            * (strategy_params.sl_multiplier - 1.)),
-                    tp: result.neckline_price
-                        + ((result.neckline_price - result.higher_price)
-                            * strategy_params.tp_multiplier),
-                    open_time: result.end_time,
-                    opening_kline: chunk[j].clone(),
-                    money: 0.,
-                    benefits: 0.,
-                    loss: 0.,
-                    taxes: 0.,
-                    lots: 0.,
-                    close_time: 0,
-                    closing_kline: None,
-                    status: Status::NotOpened,
-                    strategy: strategy_params.name,
-                });
-            } else {
-                j += 1;
-            }
-            if last_sent + 1000 < j && progression_tracker.is_some() {
-                progression_tracker
-                    .unwrap()
-                    .send(j as f32 / chunk.len() as f32 * 50.);
-                last_sent = j;
-            }
-        }
-    }
-    result_vec
+) -> Result<Vec<Trade>> {
+    create_trades(
+        klines,
+        series,
+        progression_tracker,
+        strategy_params,
+        patterns_params,
+        potential_only,
+        find_mpattern_trade,
+    )
 }
 
 pub fn create_bull_reversal_trades(
-    chunk: &Vec<MathKLine>,
-    progression_tracker: Option<&Sender<f32>>,
+    klines: &dyn KlineSource,
+    series: SeriesView,
+    progression_tracker: Option<&ProgressTracker>,
     strategy_params: StrategyParams,
     patterns_params: Arc<Vec<Arc<dyn PatternParams>>>,
     potential_only: bool,
-) -> Vec<Trade> {
-    let mut result_vec = Vec::new();
-    let mut j = 0;
-    let mut last_sent = 0;
-    while j < chunk.len() {
-        if let Some(reversal_pattern_params) =
-            patterns_params[0].downcast_ref::<ReversalPatternParams>()
-        {
-            if let Some(result) =
-                find_bull_reversal(&chunk[j..], *reversal_pattern_params, potential_only)
-            {
-                j += result.end_index;
-                result_vec.push(Trade {
-                    entry_price: result.end_price,
-                    sl: result.peak_price * strategy_params.sl_multiplier,
-                    tp: result.end_price
-                        + ((result.end_price - result.peak_price) * strategy_params.tp_multiplier),
-                    open_time: result.end_time,
-                    opening_kline: chunk[j].clone(),
-                    money: 0.,
-                    benefits: 0.,
-                    loss: 0.,
-                    taxes: 0.,
-                    lots: 0.,
-                    close_time: 0,
-                    closing_kline: None,
-                    status: Status::NotOpened,
-                    strategy: strategy_params.name,
-                });
-            } else {
-                j += 1;
-            }
-            if last_sent + 1000 < j && progression_tracker.is_some() {
-                progression_tracker
-                    .unwrap()
-                    .send(j as f32 / chunk.len() as f32 * 50.);
-                last_sent = j;
-            }
-        }
-    }
-    result_vec
+) -> Result<Vec<Trade>> {
+    create_trades(
+        klines,
+        series,
+        progression_tracker,
+        strategy_params,
+        patterns_params,
+        potential_only,
+        find_bull_reversal_trade,
+    )
 }
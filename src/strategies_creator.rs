@@ -1,17 +1,149 @@
 use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
+
 use crate::backtest::*;
+use crate::confluence::*;
+use crate::param_space::*;
 use crate::patterns::*;
 use crate::strategies;
 use crate::strategies::*;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct ParamMultiplier<T> {
     pub min: T,
     pub max: T,
     pub step: T,
 }
 
+pub fn create_w_pattern_strategy(
+    start_money: f64,
+    tp: f64,
+    sl: f64,
+    klines_repetitions: usize,
+    klines_range: usize,
+    risk: f64,
+    market_type: MarketType,
+) -> Strategy {
+    let pattern_params_w: Vec<Arc<dyn PatternParams>> = vec![Arc::new(WPatternParams {
+        klines_repetitions,
+        klines_range,
+        name: PatternName::W,
+    })];
+
+    (
+        strategies::create_wpattern_trades,
+        StrategyParams {
+            tp_multiplier: tp,
+            sl_multiplier: sl,
+            risk_per_trade: risk * 0.01,
+            money: start_money,
+            name: StrategyName::W,
+            market_type,
+        },
+        Arc::new(pattern_params_w),
+    )
+}
+
+pub fn create_m_pattern_strategy(
+    start_money: f64,
+    tp: f64,
+    sl: f64,
+    klines_repetitions: usize,
+    klines_range: usize,
+    risk: f64,
+    market_type: MarketType,
+) -> Strategy {
+    let pattern_params_m: Vec<Arc<dyn PatternParams>> = vec![Arc::new(MPatternParams {
+        klines_repetitions,
+        klines_range,
+        name: PatternName::M,
+    })];
+
+    (
+        strategies::create_mpattern_trades,
+        StrategyParams {
+            tp_multiplier: tp,
+            sl_multiplier: sl,
+            risk_per_trade: risk * 0.01,
+            money: start_money,
+            name: StrategyName::M,
+            market_type,
+        },
+        Arc::new(pattern_params_m),
+    )
+}
+
+pub fn create_reversal_pattern_strategy(
+    start_money: f64,
+    tp: f64,
+    sl: f64,
+    trend_size: usize,
+    counter_trend_size: usize,
+    risk: f64,
+    market_type: MarketType,
+) -> Strategy {
+    let reversal_pattern_params: Vec<Arc<dyn PatternParams>> =
+        vec![Arc::new(ReversalPatternParams {
+            trend_size,
+            counter_trend_size,
+            name: PatternName::BullReversal,
+        })];
+
+    (
+        strategies::create_bull_reversal_trades,
+        StrategyParams {
+            tp_multiplier: tp,
+            sl_multiplier: sl,
+            risk_per_trade: risk * 0.01,
+            money: start_money,
+            name: StrategyName::BullReversal,
+            market_type,
+        },
+        Arc::new(reversal_pattern_params),
+    )
+}
+
+// start_money, tp, sl, les deux paramètres du pattern, risk et market_type
+pub type PatternStrategyCreator = fn(f64, f64, f64, usize, usize, f64, MarketType) -> Strategy;
+
+// Les deux paramètres de pattern sont klines_repetitions/klines_range pour W et M,
+// trend_size/counter_trend_size pour BullReversal
+pub fn pattern_strategy_creator(name: StrategyName) -> Option<PatternStrategyCreator> {
+    match name {
+        StrategyName::W => Some(create_w_pattern_strategy),
+        StrategyName::M => Some(create_m_pattern_strategy),
+        StrategyName::BullReversal => Some(create_reversal_pattern_strategy),
+        StrategyName::Confluence | StrategyName::None => None,
+    }
+}
+
+// patterns commence par la règle PatternConfig::Confluence, suivie des composants
+pub fn create_confluence_strategy(
+    start_money: f64,
+    tp: f64,
+    sl: f64,
+    patterns: &[PatternConfig],
+    risk: f64,
+    market_type: MarketType,
+) -> Strategy {
+    let confluence_params: Vec<Arc<dyn PatternParams>> =
+        patterns.iter().map(PatternConfig::to_params).collect();
+
+    (
+        create_confluence_trades,
+        StrategyParams {
+            tp_multiplier: tp,
+            sl_multiplier: sl,
+            risk_per_trade: risk * 0.01,
+            money: start_money,
+            name: StrategyName::Confluence,
+            market_type,
+        },
+        Arc::new(confluence_params),
+    )
+}
+
 pub fn create_w_and_m_pattern_strategies(
     start_money: f64,
     tp: ParamMultiplier<f64>,
@@ -35,49 +167,12 @@ pub fn create_w_pattern_strategies(
     risk: ParamMultiplier<f64>,
     market_type: MarketType
 ) -> Vec<Strategy> {
-    let mut strategies: Vec<Strategy> = Vec::new();
-    let mut i = tp.min;
-    while i <= tp.max {
-        let mut j = sl.min;
-        while j <= sl.max {
-            let mut k = klines_repetitions.min;
-            while k <= klines_repetitions.max {
-                let mut l = klines_range.min;
-                while l <= klines_range.max {
-                    let mut m = risk.min;
-                    while m <= risk.max {
-                        let pattern_params_w: Vec<Arc<dyn PatternParams>> =
-                            vec![Arc::new(WPatternParams {
-                                klines_repetitions: k,
-                                klines_range: l,
-                                name: PatternName::W,
-                            })];
-
-                        strategies.push((
-                            strategies::create_wpattern_trades,
-                            StrategyParams {
-                                tp_multiplier: i,
-                                sl_multiplier: j,
-                                risk_per_trade: m * 0.01,
-                                money: start_money,
-                                name: StrategyName::W,
-                                market_type
-                            },
-                            Arc::new(pattern_params_w),
-                        ));
-
-                        m += risk.step;
-                    }
-                    l += klines_range.step;
-                }
-                k += klines_repetitions.step;
-            }
-            j += sl.step;
-        }
-        i += tp.step;
-    }
-
-    strategies
+    PatternStrategyBuilder::space(StrategyName::W, tp, sl, klines_repetitions, klines_range, risk)
+        .create_strategies(&PatternStrategyBuilder {
+            name: StrategyName::W,
+            start_money,
+            market_type,
+        })
 }
 
 pub fn create_m_pattern_strategies(
@@ -89,48 +184,12 @@ pub fn create_m_pattern_strategies(
     risk: ParamMultiplier<f64>,
     market_type: MarketType
 ) -> Vec<Strategy> {
-    let mut strategies: Vec<Strategy> = Vec::new();
-    let mut i = tp.min;
-    while i <= tp.max {
-        let mut j = sl.min;
-        while j <= sl.max {
-            let mut k = klines_repetitions.min;
-            while k <= klines_repetitions.max {
-                let mut l = klines_range.min;
-                while l <= klines_range.max {
-                    let mut m = risk.min;
-                    while m <= risk.max {
-                        let pattern_params_m: Vec<Arc<dyn PatternParams>> =
-                            vec![Arc::new(MPatternParams {
-                                klines_repetitions: k,
-                                klines_range: l,
-                                name: PatternName::M,
-                            })];
-
-                        strategies.push((
-                            strategies::create_mpattern_trades,
-                            StrategyParams {
-                                tp_multiplier: i,
-                                sl_multiplier: j,
-                                risk_per_trade: m * 0.01,
-                                money: start_money,
-                                name: StrategyName::M,
-                                market_type
-                            },
-                            Arc::new(pattern_params_m),
-                        ));
-                        m += risk.step;
-                    }
-                    l += klines_range.step;
-                }
-                k += klines_repetitions.step;
-            }
-            j += sl.step;
-        }
-        i += tp.step;
-    }
-
-    strategies
+    PatternStrategyBuilder::space(StrategyName::M, tp, sl, klines_repetitions, klines_range, risk)
+        .create_strategies(&PatternStrategyBuilder {
+            name: StrategyName::M,
+            start_money,
+            market_type,
+        })
 }
 
 pub fn create_reversal_pattern_strategies(
@@ -142,45 +201,17 @@ pub fn create_reversal_pattern_strategies(
     risk: ParamMultiplier<f64>,
     market_type: MarketType
 ) -> Vec<Strategy> {
-    let mut strategies: Vec<Strategy> = Vec::new();
-    let mut i = tp.min;
-    while i <= tp.max {
-        let mut j = sl.min;
-        while j <= sl.max {
-            let mut k: usize = trend_size.min;
-            while k <= trend_size.max {
-                let mut l = counter_trend_size.min;
-                while l <= counter_trend_size.max {
-                    let mut m = risk.min;
-                    while m <= risk.max {
-                        let reversal_pattern_params: Vec<Arc<dyn PatternParams>> =
-                            vec![Arc::new(ReversalPatternParams {
-                                trend_size: k,
-                                counter_trend_size: l,
-                                name: PatternName::BullReversal,
-                            })];
-
-                        strategies.push((
-                            strategies::create_bull_reversal_trades,
-                            StrategyParams {
-                                tp_multiplier: i,
-                                sl_multiplier: j,
-                                risk_per_trade: m * 0.01,
-                                money: start_money,
-                                name: StrategyName::BullReversal,
-                                market_type
-                            },
-                            Arc::new(reversal_pattern_params),
-                        ));
-                        m += risk.step;
-                    }
-                    l += counter_trend_size.step;
-                }
-                k += trend_size.step;
-            }
-            j += sl.step;
-        }
-        i += tp.step;
-    }
-    strategies
-}
\ No newline at end of file
+    PatternStrategyBuilder::space(
+        StrategyName::BullReversal,
+        tp,
+        sl,
+        trend_size,
+        counter_trend_size,
+        risk,
+    )
+    .create_strategies(&PatternStrategyBuilder {
+        name: StrategyName::BullReversal,
+        start_money,
+        market_type,
+    })
+}
@@ -0,0 +1,70 @@
+use crate::series::*;
+
+const MINUTE: i64 = 60 * 1000;
+
+// Durée en millisecondes d'un intervalle Binance ("15m", "4h", "1d"...), None pour les mois
+pub fn interval_millis(interval: &str) -> Option<i64> {
+    let split = interval.len().checked_sub(1)?;
+    let (count, unit) = interval.split_at(split);
+    let count: i64 = count.parse().ok().filter(|count| *count > 0)?;
+    let unit = match unit {
+        "s" => 1000,
+        "m" => MINUTE,
+        "h" => 60 * MINUTE,
+        "d" => 24 * 60 * MINUTE,
+        "w" => 7 * 24 * 60 * MINUTE,
+        _ => return None,
+    };
+    Some(count * unit)
+}
+
+// Agrège les klines par tranche de interval millisecondes alignée sur l'epoch, comme Binance.
+// La dernière tranche peut être incomplète : sa close_time est celle de la tranche entière,
+// elle n'est donc jamais visible avant la fin des données.
+pub fn resample(series: SeriesView, interval: i64) -> PriceSeries {
+    let mut result = PriceSeries::new();
+    for i in 0..series.len() {
+        let bucket = series.open_time[i] - series.open_time[i].rem_euclid(interval);
+        if result.open_time.last() == Some(&bucket) {
+            let last = result.len() - 1;
+            result.high[last] = result.high[last].max(series.high[i]);
+            result.low[last] = result.low[last].min(series.low[i]);
+            result.close[last] = series.close[i];
+            result.volume[last] += series.volume[i];
+        } else {
+            result.push_from(series, i);
+            let last = result.len() - 1;
+            result.open_time[last] = bucket;
+            result.close_time[last] = bucket + interval - 1;
+        }
+    }
+    result
+}
+
+// Série d'une unité de temps supérieure, interrogée depuis les klines de base :
+// une kline n'est visible qu'une fois clôturée
+#[derive(Clone, Debug)]
+pub struct AlignedTimeframe {
+    pub interval: i64,
+    pub series: PriceSeries,
+}
+
+impl AlignedTimeframe {
+    pub fn new(interval: i64, series: PriceSeries) -> Self {
+        AlignedTimeframe { interval, series }
+    }
+
+    pub fn from_base(base: SeriesView, interval: i64) -> Self {
+        AlignedTimeframe::new(interval, resample(base, interval))
+    }
+
+    // Klines clôturées à la date time
+    pub fn visible(&self, time: i64) -> SeriesView<'_> {
+        let series = self.series.view();
+        series.slice(0..series.first_closing_at(time + 1))
+    }
+
+    pub fn last_closed(&self, time: i64) -> Option<usize> {
+        self.visible(time).len().checked_sub(1)
+    }
+}
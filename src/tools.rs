@@ -1,6 +1,8 @@
 use std::{fs::File, io::Write};
 use binance::{market::Market, model::{KlineSummary, KlineSummaries}};
 
+use crate::error::{Error, Result};
+
 pub fn retreive_test_data(
     server_time: u64,
     market: &Market,
@@ -10,7 +12,10 @@ pub fn retreive_test_data(
     iterations: usize,
     batch_size: u16,
     write_in_file: bool
-) -> Vec<KlineSummary> {
+) -> Result<Vec<KlineSummary>> {
+    if iterations == 0 {
+        return Err(Error::InvalidParams(String::from("iterations must be at least 1")));
+    }
     let mut i: u64 = iterations as u64;
     let start_i = i;
     let mut j = 0;
@@ -18,26 +23,15 @@ pub fn retreive_test_data(
     let mut end_time = server_time - ((i - 1) * 60 * 1000 * 1000);
 
     let mut klines = Vec::new();
-    println!("symbol = {}, interval = {}", symbol, interval);
-    if let Err(retreive_klines) = market.get_klines(
-        symbol.clone(),
-        interval.clone(),
-        batch_size,
-        start_time,
-        end_time,) {
-            println!("Error while retreiving klines from binance. Error : {:#?}", retreive_klines);
-        }
-
-    while let Ok(retreive_klines) = market.get_klines(
-        symbol.clone(),
-        interval.clone(),
-        batch_size,
-        start_time,
-        end_time,
-    ) {
-        if i == 0 {
-            break;
-        }
+    log::info!("symbol = {}, interval = {}", symbol, interval);
+    while i > 0 {
+        let retreive_klines = market.get_klines(
+            symbol.clone(),
+            interval.clone(),
+            batch_size,
+            start_time,
+            end_time,
+        )?;
         if let KlineSummaries::AllKlineSummaries(mut retreived_vec) = retreive_klines {
             klines.append(&mut retreived_vec);
         }
@@ -48,14 +42,14 @@ pub fn retreive_test_data(
         i -= 1;
         j += 1;
         if i % 10 == 0 {
-            println!("Retreived {}/{} bench of klines data", j, start_i);
+            log::info!("Retreived {}/{} bench of klines data", j, start_i);
         }
     } 
 
     if write_in_file {
-        write_data_to_file(&klines, symbol, interval, folder);
+        write_data_to_file(&klines, symbol, interval, folder)?;
     }
-    klines
+    Ok(klines)
 }
 
 pub fn write_data_to_file(
@@ -63,12 +57,12 @@ pub fn write_data_to_file(
     symbol: String,
     interval: String,
     folder: String,
-) {
-    let serialized = serde_json::to_string_pretty(klines).unwrap();
+) -> Result<()> {
+    let serialized = serde_json::to_string_pretty(klines)?;
     let mut file = File::create(format!(
         "{}{}-{}.json",
         folder, symbol, interval
-    ))
-    .unwrap();
-    file.write_all(serialized.as_bytes()).unwrap();
-}
\ No newline at end of file
+    ))?;
+    file.write_all(serialized.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::account::*;
+use crate::backtest::*;
+use crate::error::{Error, Result};
+use crate::metrics::*;
+use crate::patterns::*;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum WindowMode {
+    // La fenêtre in-sample glisse avec la fenêtre out-of-sample
+    Rolling,
+    // La fenêtre in-sample commence toujours à la première kline
+    Anchored,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct WalkForwardConfig {
+    pub in_sample_size: usize,
+    pub out_of_sample_size: usize,
+    pub mode: WindowMode,
+    pub metric: Metric,
+    pub portfolio_limits: PortfolioLimits,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WalkForwardWindow {
+    pub in_sample: (usize, usize),
+    pub out_of_sample: (usize, usize),
+    pub best_strategy_index: usize,
+    pub in_sample_result: StrategyResult,
+    pub out_of_sample_result: StrategyResult,
+    // Rendement par kline out-of-sample divisé par le rendement par kline in-sample
+    pub efficiency: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WalkForwardReport {
+    pub windows: Vec<WalkForwardWindow>,
+    pub stitched_equity: Vec<f64>,
+    pub start_money: f64,
+    pub final_money: f64,
+    pub walk_forward_efficiency: Option<f64>,
+}
+
+fn run(
+    klines: &[MathKLine],
+    strategies: &[Strategy],
+    limits: PortfolioLimits,
+) -> Result<Vec<StrategyResult>> {
+    Ok(
+        Backtester::new(Arc::new(klines.to_vec()), None, None, false)
+            .set_portfolio_limits(limits)
+            .add_strategies(&mut strategies.to_vec())
+            .start()?
+            .get_results(),
+    )
+}
+
+fn efficiency(
+    in_sample_return: f64,
+    in_sample_len: usize,
+    out_of_sample_return: f64,
+    out_of_sample_len: usize,
+) -> Option<f64> {
+    if in_sample_return <= 0. || in_sample_len == 0 || out_of_sample_len == 0 {
+        return None;
+    }
+    Some(
+        (out_of_sample_return / out_of_sample_len as f64)
+            / (in_sample_return / in_sample_len as f64),
+    )
+}
+
+pub fn walk_forward(
+    klines: &[MathKLine],
+    strategies: &[Strategy],
+    config: WalkForwardConfig,
+) -> Result<WalkForwardReport> {
+    if config.in_sample_size == 0 || config.out_of_sample_size == 0 {
+        return Err(Error::InvalidParams(String::from(
+            "in-sample and out-of-sample sizes must be at least 1",
+        )));
+    }
+    let needed = config.in_sample_size + config.out_of_sample_size;
+    if klines.len() < needed {
+        return Err(Error::InsufficientData {
+            needed,
+            available: klines.len(),
+        });
+    }
+
+    let start_money = strategies.first().map_or(0., |strategy| strategy.1.money);
+    let mut windows = Vec::new();
+    let mut stitched_equity = Vec::new();
+    let mut equity = start_money;
+    let mut in_sample_returns = 0.;
+    let mut in_sample_bars = 0;
+    let mut out_of_sample_returns = 0.;
+    let mut out_of_sample_bars = 0;
+
+    let mut offset = 0;
+    while offset + needed <= klines.len() {
+        let in_sample = match config.mode {
+            WindowMode::Rolling => (offset, offset + config.in_sample_size),
+            WindowMode::Anchored => (0, offset + config.in_sample_size),
+        };
+        let out_of_sample = (in_sample.1, in_sample.1 + config.out_of_sample_size);
+        offset += config.out_of_sample_size;
+
+        let in_sample_results = run(
+            &klines[in_sample.0..in_sample.1],
+            strategies,
+            config.portfolio_limits,
+        )?;
+        let best_strategy_index = match best_result_index(&in_sample_results, config.metric) {
+            Some(index) => index,
+            None => continue,
+        };
+        let out_of_sample_result = run(
+            &klines[out_of_sample.0..out_of_sample.1],
+            &strategies[best_strategy_index..=best_strategy_index],
+            config.portfolio_limits,
+        )?
+        .remove(0);
+        let in_sample_result = in_sample_results[best_strategy_index].clone();
+
+        let in_sample_return = total_return(&in_sample_result);
+        let out_of_sample_return = total_return(&out_of_sample_result);
+        in_sample_returns += in_sample_return;
+        in_sample_bars += in_sample.1 - in_sample.0;
+        out_of_sample_returns += out_of_sample_return;
+        out_of_sample_bars += out_of_sample.1 - out_of_sample.0;
+
+        // Les résultats out-of-sample sont enchaînés en réinvestissant le capital
+        let scale = if out_of_sample_result.start_money == 0. {
+            0.
+        } else {
+            equity / out_of_sample_result.start_money
+        };
+        stitched_equity.extend(
+            out_of_sample_result
+                .money_evolution
+                .iter()
+                .map(|money| money * scale),
+        );
+        equity = out_of_sample_result.final_money * scale;
+
+        windows.push(WalkForwardWindow {
+            in_sample,
+            out_of_sample,
+            best_strategy_index,
+            efficiency: efficiency(
+                in_sample_return,
+                in_sample.1 - in_sample.0,
+                out_of_sample_return,
+                out_of_sample.1 - out_of_sample.0,
+            ),
+            in_sample_result,
+            out_of_sample_result,
+        });
+    }
+
+    Ok(WalkForwardReport {
+        windows,
+        stitched_equity,
+        start_money,
+        final_money: equity,
+        walk_forward_efficiency: efficiency(
+            in_sample_returns,
+            in_sample_bars,
+            out_of_sample_returns,
+            out_of_sample_bars,
+        ),
+    })
+}